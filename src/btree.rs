@@ -0,0 +1,1148 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io::{prelude::*, Cursor, SeekFrom};
+use std::ops::RangeInclusive;
+
+use crate::database::DB_HEADER_SIZE;
+use crate::header::DbHeader;
+use crate::record::parse_record;
+use crate::value::compare_values;
+use crate::varint::parse_varint;
+use crate::{SQLiteInternalError, TextEncoding, Value};
+
+/// B-tree page types, given by the first byte of the page header.
+///
+/// B-tree pages doc: https://www.sqlite.org/fileformat2.html#b_tree_pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageType {
+    InteriorIndex, // 0x02
+    InteriorTable, // 0x05
+    LeafIndex,     // 0x0a
+    LeafTable,     // 0x0d
+}
+
+impl TryFrom<u8> for PageType {
+    type Error = SQLiteInternalError;
+
+    fn try_from(byte: u8) -> Result<PageType, Self::Error> {
+        match byte {
+            0x02 => Ok(PageType::InteriorIndex),
+            0x05 => Ok(PageType::InteriorTable),
+            0x0a => Ok(PageType::LeafIndex),
+            0x0d => Ok(PageType::LeafTable),
+            _ => Err(SQLiteInternalError::BadPageType(byte)),
+        }
+    }
+}
+
+impl PageType {
+    fn is_leaf(self) -> bool {
+        matches!(self, PageType::LeafIndex | PageType::LeafTable)
+    }
+
+    /// 'The b-tree page header is 8 bytes in size for leaf pages and 12 bytes for interior pages.'
+    fn header_size(self) -> usize {
+        if self.is_leaf() {
+            8
+        } else {
+            12
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PageHeader {
+    pub(crate) page_type: PageType,
+    pub(crate) first_freeblock: u16, // offset in the page, 0 if there are no freeblocks
+    pub(crate) nb_cells: u16,
+    pub(crate) cell_content_start: u32, // offset in the page of the cell content area
+    pub(crate) fragmented_free_bytes: u8,
+    pub(crate) rightmost_pointer: Option<u32>, // only interior pages have one, at offset 8
+}
+
+/// Read a b-tree page header, starting from the current reader position.
+///
+/// The header is 8 or 12 bytes long depending on the page type. Either way, the reader is left at
+/// the start of the cell pointer array, which immediately follows the header.
+pub(crate) fn read_page_header(
+    db: &mut (impl Read + Seek),
+) -> Result<PageHeader, SQLiteInternalError> {
+    let mut header_bytes = [0; 12];
+    db.read_exact(&mut header_bytes[..8])
+        .map_err(SQLiteInternalError::ReadError)?;
+    let page_type = PageType::try_from(header_bytes[0])?;
+
+    db.read_exact(&mut header_bytes[8..page_type.header_size()])
+        .map_err(SQLiteInternalError::ReadError)?;
+
+    // 'The two-byte integer at offset 1 gives the start of the first freeblock on the page, or is
+    // zero if there are no freeblocks.'
+    let first_freeblock = u16::from_be_bytes([header_bytes[1], header_bytes[2]]);
+    // 'The two-byte integer at offset 3 gives the number of cells on the page.'
+    let nb_cells = u16::from_be_bytes([header_bytes[3], header_bytes[4]]);
+    // 'The two-byte integer at offset 5 designates the start of the cell content area. A zero
+    // value for this integer is interpreted as 65536.'
+    let cell_content_start = match u16::from_be_bytes([header_bytes[5], header_bytes[6]]) {
+        0 => 65536,
+        offset => u32::from(offset),
+    };
+    // 'The one-byte integer at offset 7 gives the number of fragmented free bytes within the cell
+    // content area.'
+    let fragmented_free_bytes = header_bytes[7];
+    let rightmost_pointer = (!page_type.is_leaf()).then(|| {
+        u32::from_be_bytes([
+            header_bytes[8],
+            header_bytes[9],
+            header_bytes[10],
+            header_bytes[11],
+        ])
+    });
+
+    Ok(PageHeader {
+        page_type,
+        first_freeblock,
+        nb_cells,
+        cell_content_start,
+        fragmented_free_bytes,
+        rightmost_pointer,
+    })
+}
+
+/// Varint:
+/// A variable-length integer or "varint" is a static Huffman encoding of 64-bit twos-complement integers that uses less space for small positive values. A varint is between 1 and 9 bytes in length. The varint consists of either zero or more bytes which have the high-order bit set followed by a single byte with the high-order bit clear, or nine bytes, whichever is shorter. The lower seven bits of each of the first eight bytes and all 8 bits of the ninth byte are used to reconstruct the 64-bit twos-complement integer. Varints are big-endian: bits taken from the earlier byte of the varint are more significant than bits taken from the later bytes.
+///
+/// "The cell pointer array of a b-tree page immediately follows the b-tree page header. Let K be the number of cells on the btree. The cell pointer array consists of K 2-byte integer offsets to the cell contents."
+/// And codecrafters add: "The offsets are relative to the start of the page".
+///
+/// The offsets are in key order, but the cells themselves may be anywhere in the cell content
+/// area, in any order: e.g. a cell inserted last may reuse a freeblock between older cells. Cells
+/// are always read by seeking to `page_offset + cell_offset`, never by reading on from the previous
+/// cell.
+pub(crate) fn get_cell_ptr_array(
+    nb_cells: u16,
+    b_tree_page_content: &mut (impl Read + Seek),
+) -> Result<Vec<u16>, SQLiteInternalError> {
+    // An empty page, e.g. the root page of a table with no rows, has nothing to read
+    if nb_cells == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut offsets_array_buff: Vec<u8> = vec![0; 2 * usize::from(nb_cells)];
+    b_tree_page_content
+        .read_exact(&mut offsets_array_buff)
+        .map_err(SQLiteInternalError::ReadError)?;
+
+    let offsets_array: Vec<u16> = offsets_array_buff
+        .chunks_exact(2)
+        .map(|chunk| {
+            let cell_offset_bytes: [u8; 2] = chunk
+                .try_into()
+                .expect("expect cell offsets array to have an even number of bytes");
+            u16::from_be_bytes(cell_offset_bytes)
+        })
+        .collect();
+    Ok(offsets_array)
+}
+
+/// Where pages are in the file, and which ones are b-tree pages.
+///
+/// Read from the database header: https://www.sqlite.org/fileformat2.html#the_database_header
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PageLayout {
+    pub(crate) page_size: u64,
+    /// Page size minus the bytes reserved at the end of each page
+    pub(crate) usable_size: u64,
+    /// Number of pages, derived from the file size
+    pub(crate) page_count: u64,
+    /// For auto_vacuum databases: number of entries on a pointer-map page
+    ptrmap_entries_per_page: Option<u64>,
+}
+
+impl PageLayout {
+    /// Read the page layout from the database header: the page size, the bytes reserved at the
+    /// end of each page, and whether the database is in auto-vacuum mode, i.e. has pointer-map
+    /// pages.
+    pub(crate) fn read(db: &mut (impl Read + Seek)) -> Result<PageLayout, SQLiteInternalError> {
+        let db_header = DbHeader::read(db)?;
+        let page_size = u64::from(db_header.page_size);
+        let reserved_space = u64::from(db_header.reserved_space);
+
+        let file_size = db
+            .seek(SeekFrom::End(0))
+            .map_err(SQLiteInternalError::SeekError)?;
+        let page_count = file_size / page_size;
+
+        // Each pointer-map entry is 5 bytes long
+        let ptrmap_entries_per_page =
+            (db_header.largest_root_page != 0).then(|| (page_size - reserved_space) / 5);
+
+        Ok(PageLayout {
+            page_size,
+            usable_size: page_size - reserved_space,
+            page_count,
+            ptrmap_entries_per_page,
+        })
+    }
+
+    /// Offset of the start of the page in the file. Page numbers start at 1.
+    pub(crate) fn page_offset(&self, page_number: u32) -> u64 {
+        self.page_size * u64::from(page_number - 1)
+    }
+
+    /// In auto_vacuum databases, pointer-map pages are interspersed with the b-tree pages[1]: the
+    /// first one is page 2, and each one is followed by the pages whose entries it holds.
+    ///
+    /// B-trees never point to them, so a traversal only lands on one if the file is corrupt.
+    /// Still, their first byte can look like a b-tree page type (entry types are 1 to 5), so they
+    /// must be told apart explicitly.
+    ///
+    /// [1]: https://www.sqlite.org/fileformat2.html#pointer_map_or_ptrmap_pages
+    fn is_ptrmap_page(&self, page_number: u32) -> bool {
+        match self.ptrmap_entries_per_page {
+            Some(entries_per_page) => {
+                let page_number = u64::from(page_number);
+                page_number >= 2 && (page_number - 2) % (entries_per_page + 1) == 0
+            }
+            None => false,
+        }
+    }
+
+    /// Seek to the start of a b-tree page.
+    ///
+    /// The page must be whole in the file: the page count is derived from the file size, so a
+    /// page past it is missing or cut short, e.g. after an interrupted download. Page numbers
+    /// start at 1: a child pointer of 0 only comes from a corrupt file.
+    pub(crate) fn seek_btree_page(
+        &self,
+        page_number: u32,
+        db: &mut (impl Read + Seek),
+    ) -> Result<u64, SQLiteInternalError> {
+        if page_number == 0 {
+            return Err(SQLiteInternalError::NullPageNumber);
+        }
+        if u64::from(page_number) > self.page_count {
+            return Err(SQLiteInternalError::TruncatedFile(page_number));
+        }
+        if self.is_ptrmap_page(page_number) {
+            return Err(SQLiteInternalError::UnexpectedPtrmapPage(page_number));
+        }
+        let page_offset = self.page_offset(page_number);
+        // The b-tree page header of page 1 comes after the database header, but cell offsets are
+        // still relative to the start of the page
+        let page_header_offset = if page_number == 1 {
+            DB_HEADER_SIZE
+        } else {
+            page_offset
+        };
+        db.seek(SeekFrom::Start(page_header_offset))
+            .map_err(SQLiteInternalError::SeekError)?;
+        Ok(page_offset)
+    }
+}
+
+/// Space usage of a b-tree page, for diagnostics.
+///
+/// Free space on a page is made of:
+/// - the unallocated space between the cell pointer array and the cell content area
+/// - freeblocks: freed areas of the cell content area, chained by a 2-byte offset of the next
+///   freeblock followed by the 2-byte size of the freeblock
+/// - fragments: groups of less than 4 free bytes, too small to be freeblocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageInfo {
+    pub page_type: PageType,
+    pub nb_cells: u16,
+    pub unallocated_bytes: u64,
+    pub freeblocks: Vec<(u16, u16)>, // offset in the page and size of each freeblock, in order
+    pub fragmented_free_bytes: u8,
+}
+
+impl PageInfo {
+    /// Total number of free bytes on the page.
+    pub fn free_bytes(&self) -> u64 {
+        let freeblock_bytes = self
+            .freeblocks
+            .iter()
+            .map(|&(_, size)| u64::from(size))
+            .sum::<u64>();
+        self.unallocated_bytes + freeblock_bytes + u64::from(self.fragmented_free_bytes)
+    }
+}
+
+/// Read the space usage of a b-tree page, walking its freeblock chain.
+pub(crate) fn read_page_info(
+    page_number: u32,
+    page_layout: PageLayout,
+    db: &mut (impl Read + Seek),
+) -> Result<PageInfo, SQLiteInternalError> {
+    let page_offset = page_layout.seek_btree_page(page_number, db)?;
+    let page_header = read_page_header(db)?;
+
+    // The cell pointer array follows the page header, itself after the database header on page 1
+    let page_header_start = if page_number == 1 { DB_HEADER_SIZE } else { 0 };
+    let cell_ptr_array_end = page_header_start
+        + page_header.page_type.header_size() as u64
+        + 2 * u64::from(page_header.nb_cells);
+    let unallocated_bytes = u64::from(page_header.cell_content_start)
+        .checked_sub(cell_ptr_array_end)
+        .ok_or(SQLiteInternalError::BadFreeSpace(page_number))?;
+
+    // Freeblocks are chained in increasing offset order, which also rules out cycles
+    let mut freeblocks = Vec::new();
+    let mut freeblock_offset = page_header.first_freeblock;
+    while freeblock_offset != 0 {
+        let is_in_order = !matches!(
+            freeblocks.last(),
+            Some(&(previous_offset, _)) if freeblock_offset <= previous_offset
+        );
+        if !is_in_order || u64::from(freeblock_offset) + 4 > page_layout.page_size {
+            return Err(SQLiteInternalError::BadFreeSpace(page_number));
+        }
+
+        db.seek(SeekFrom::Start(page_offset + u64::from(freeblock_offset)))
+            .map_err(SQLiteInternalError::SeekError)?;
+        let mut freeblock_header = [0; 4];
+        db.read_exact(&mut freeblock_header)
+            .map_err(SQLiteInternalError::ReadError)?;
+        let size = u16::from_be_bytes([freeblock_header[2], freeblock_header[3]]);
+        freeblocks.push((freeblock_offset, size));
+        freeblock_offset = u16::from_be_bytes([freeblock_header[0], freeblock_header[1]]);
+    }
+
+    Ok(PageInfo {
+        page_type: page_header.page_type,
+        nb_cells: page_header.nb_cells,
+        unallocated_bytes,
+        freeblocks,
+        fragmented_free_bytes: page_header.fragmented_free_bytes,
+    })
+}
+
+/// Guards a b-tree traversal against corrupt (or malicious) files.
+///
+/// A b-tree is a tree: a traversal never visits a page twice. A child pointer leading back to an
+/// already visited page means the file is corrupt, and following it would loop forever. The
+/// number of visited pages is capped as well.
+///
+/// Visited pages can also be traced to stderr, to follow the traversal.
+#[derive(Debug)]
+pub(crate) struct PageGuard {
+    visited: HashSet<u32>,
+    max_pages: u64,
+    trace: bool,
+}
+
+impl PageGuard {
+    pub(crate) fn new(max_pages: u64, trace: bool) -> Self {
+        PageGuard {
+            visited: HashSet::new(),
+            max_pages,
+            trace,
+        }
+    }
+
+    fn visit(&mut self, page_number: u32) -> Result<(), SQLiteInternalError> {
+        if !self.visited.insert(page_number) {
+            return Err(SQLiteInternalError::PageCycle(page_number));
+        }
+        if self.visited.len() as u64 > self.max_pages {
+            return Err(SQLiteInternalError::TooManyPages(self.max_pages));
+        }
+        Ok(())
+    }
+}
+
+/// Visit a page of a b-tree traversal: seek to it and read its header.
+///
+/// Returns the page offset and the page header, the reader being left at the start of the cell
+/// pointer array.
+fn read_btree_page(
+    page_number: u32,
+    page_layout: PageLayout,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<(u64, PageHeader), SQLiteInternalError> {
+    page_guard.visit(page_number)?;
+    let page_offset = page_layout.seek_btree_page(page_number, db)?;
+    let page_header = read_page_header(db)?;
+    if page_guard.trace {
+        eprintln!(
+            "trace: read page {page_number} ({:?}, {} cells)",
+            page_header.page_type, page_header.nb_cells
+        );
+    }
+    Ok((page_offset, page_header))
+}
+
+/// Walk the table b-tree rooted at the given page, and collect the location of every cell found
+/// in its leaf pages, in rowid order.
+///
+/// Returns (page offset, cell offset) pairs. Cell offsets are relative to the start of their page.
+///
+/// B-tree pages doc: https://www.sqlite.org/fileformat2.html#b_tree_pages
+/// - leaf table page (0x0d): cells hold the rows
+/// - interior table page (0x05): each cell holds a 4-byte left child page number followed by a
+///   rowid varint (the largest rowid in that subtree). The right-most child page number is not in
+///   a cell, it is stored in the page header at offset 8 and must be visited after all the cells.
+pub(crate) fn table_leaf_cells(
+    page_number: u32,
+    page_layout: PageLayout,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<(u64, u16)>, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+    match page_header.page_type {
+        PageType::LeafTable => Ok(cell_ptr_array
+            .into_iter()
+            .map(|cell_offset| (page_offset, cell_offset))
+            .collect()),
+        PageType::InteriorTable => {
+            let mut child_pages = Vec::with_capacity(cell_ptr_array.len() + 1);
+            for cell_offset in cell_ptr_array {
+                db.seek(SeekFrom::Start(page_offset + u64::from(cell_offset)))
+                    .map_err(SQLiteInternalError::SeekError)?;
+                let mut left_child_bytes = [0; 4];
+                db.read_exact(&mut left_child_bytes)
+                    .map_err(SQLiteInternalError::ReadError)?;
+                child_pages.push(u32::from_be_bytes(left_child_bytes));
+            }
+            child_pages.push(
+                page_header
+                    .rightmost_pointer
+                    .expect("interior pages have a right-most pointer"),
+            );
+
+            let mut leaf_cells = Vec::new();
+            for child_page in child_pages {
+                leaf_cells.extend(table_leaf_cells(child_page, page_layout, page_guard, db)?);
+            }
+            Ok(leaf_cells)
+        }
+        PageType::InteriorIndex | PageType::LeafIndex => Err(
+            SQLiteInternalError::UnexpectedPageType(page_header.page_type),
+        ),
+    }
+}
+
+/// Walk the table b-tree rooted at the given page, and collect the location of the cells of the
+/// rows whose rowid is in the given range, in rowid order.
+///
+/// Only the subtrees that may hold such rows are visited: the walk descends to the lower bound,
+/// then scans forward until the upper bound.
+pub(crate) fn table_leaf_cells_in_range(
+    page_number: u32,
+    page_layout: PageLayout,
+    rowids: &RangeInclusive<i64>,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<(u64, u16)>, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+    let mut leaf_cells = Vec::new();
+    match page_header.page_type {
+        PageType::LeafTable => {
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                let (_payload_size, payload_size_varint) = parse_varint(offset, db)?;
+                let (rowid, _) = parse_varint(offset + payload_size_varint as u64, db)?;
+                let rowid = rowid as i64;
+                if rowid > *rowids.end() {
+                    break;
+                }
+                if rowid >= *rowids.start() {
+                    leaf_cells.push((page_offset, cell_offset));
+                }
+            }
+        }
+        PageType::InteriorTable => {
+            // The left child of a cell holds the rowids after the previous cell's key, up to and
+            // including the cell's key
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                db.seek(SeekFrom::Start(offset))
+                    .map_err(SQLiteInternalError::SeekError)?;
+                let mut left_child_bytes = [0; 4];
+                db.read_exact(&mut left_child_bytes)
+                    .map_err(SQLiteInternalError::ReadError)?;
+                let (key, _) = parse_varint(offset + 4, db)?;
+                let key = key as i64;
+                if key >= *rowids.start() {
+                    let left_child = u32::from_be_bytes(left_child_bytes);
+                    leaf_cells.extend(table_leaf_cells_in_range(
+                        left_child,
+                        page_layout,
+                        rowids,
+                        page_guard,
+                        db,
+                    )?);
+                    if key >= *rowids.end() {
+                        return Ok(leaf_cells);
+                    }
+                }
+            }
+            let rightmost_child = page_header
+                .rightmost_pointer
+                .expect("interior pages have a right-most pointer");
+            leaf_cells.extend(table_leaf_cells_in_range(
+                rightmost_child,
+                page_layout,
+                rowids,
+                page_guard,
+                db,
+            )?);
+        }
+        PageType::InteriorIndex | PageType::LeafIndex => {
+            return Err(SQLiteInternalError::UnexpectedPageType(
+                page_header.page_type,
+            ))
+        }
+    }
+    Ok(leaf_cells)
+}
+
+/// Read a cell from a table b-tree leaf page, at a given cell offset.
+///
+/// Cell structure:
+/// - payload size (varint): 'the total number of bytes of payload, including any overflow'
+/// - rowid (varint)
+/// - payload: the 'record'
+///
+/// Returns the rowid and the payload bytes, overflow included.
+/// Rowids are signed 64-bit integers: the rowid varint is reinterpreted as twos-complement.
+pub(crate) fn read_table_leaf_cell(
+    page_offset: u64,
+    cell_offset: u16,
+    page_layout: PageLayout,
+    db: &mut (impl Read + Seek),
+) -> Result<(i64, Vec<u8>), SQLiteInternalError> {
+    let mut offset = page_offset + u64::from(cell_offset);
+
+    let (payload_size, payload_size_varint) = parse_varint(offset, db)?;
+
+    // Next, the rowid
+    offset += payload_size_varint as u64;
+    let (rowid, rowid_varint_size) = parse_varint(offset, db)?;
+
+    offset += rowid_varint_size as u64;
+    // 'X is U-35 for table-btree leaf pages'
+    let max_local = page_layout.usable_size - 35;
+    let payload = read_payload(offset, payload_size, max_local, page_layout, db)?;
+
+    Ok((rowid as i64, payload))
+}
+
+/// The payload of a table leaf cell, borrowed from the bytes of the whole file, if it is stored
+/// entirely in the cell. A payload spilling onto overflow pages is not contiguous: `None` then.
+pub(crate) fn local_table_leaf_payload(
+    file: &[u8],
+    page_offset: u64,
+    cell_offset: u16,
+    page_layout: PageLayout,
+) -> Result<Option<&[u8]>, SQLiteInternalError> {
+    let mut offset = page_offset + u64::from(cell_offset);
+    let (payload_size, payload_size_varint) = parse_varint(offset, &mut Cursor::new(file))?;
+    offset += payload_size_varint as u64;
+    let (_rowid, rowid_varint_size) = parse_varint(offset, &mut Cursor::new(file))?;
+    offset += rowid_varint_size as u64;
+
+    // 'X is U-35 for table-btree leaf pages'
+    let max_local = page_layout.usable_size - 35;
+    if payload_size > max_local {
+        return Ok(None);
+    }
+    let payload = usize::try_from(offset)
+        .ok()
+        .and_then(|start| file.get(start..start + payload_size as usize))
+        .ok_or(SQLiteInternalError::PayloadTooLarge(payload_size))?;
+    Ok(Some(payload))
+}
+
+/// Read a cell payload of the given size, starting at the given offset.
+///
+/// A payload too large for its page spills onto overflow pages[1]: the first bytes are stored in
+/// the cell, followed by the 4-byte page number of the first overflow page. Each overflow page
+/// holds the 4-byte page number of the next one (0 for the last one), then more payload bytes.
+/// A payload of up to `max_local` bytes, the maximum stored in the cell, fits entirely in it,
+/// without any overflow page number: an exact fit does not overflow.
+///
+/// [1]: https://www.sqlite.org/fileformat2.html#cell_payload
+fn read_payload(
+    offset: u64,
+    payload_size: u64,
+    max_local: u64,
+    page_layout: PageLayout,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<u8>, SQLiteInternalError> {
+    let usable_size = page_layout.usable_size;
+    let local_size = if payload_size <= max_local {
+        payload_size
+    } else {
+        // 'M is always ((U-12)*32/255)-23', 'K is M+((P-M)%(U-4))': K bytes are stored locally if
+        // K <= X, else M bytes
+        let min_local = (usable_size - 12) * 32 / 255 - 23;
+        let local_size = min_local + (payload_size - min_local) % (usable_size - 4);
+        if local_size <= max_local {
+            local_size
+        } else {
+            min_local
+        }
+    };
+
+    // The payload size comes from the file: check it before allocating a buffer for it. The
+    // overflowing part needs a page for each chunk, so it cannot take more than the whole file.
+    let max_overflow_size = page_layout.page_count.saturating_mul(usable_size - 4);
+    if payload_size - local_size > max_overflow_size {
+        return Err(SQLiteInternalError::PayloadTooLarge(payload_size));
+    }
+
+    db.seek(SeekFrom::Start(offset))
+        .map_err(SQLiteInternalError::SeekError)?;
+    let mut payload = vec![0; payload_size as usize];
+    db.read_exact(&mut payload[..local_size as usize])
+        .map_err(SQLiteInternalError::ReadError)?;
+    if local_size == payload_size {
+        return Ok(payload);
+    }
+
+    let mut page_number_bytes = [0; 4];
+    db.read_exact(&mut page_number_bytes)
+        .map_err(SQLiteInternalError::ReadError)?;
+    // Each overflow page is filled before the next one: the chain ends with the payload
+    for chunk in payload[local_size as usize..].chunks_mut(usable_size as usize - 4) {
+        let page_number = u32::from_be_bytes(page_number_bytes);
+        if page_number == 0 {
+            return Err(SQLiteInternalError::MalformedRecord);
+        }
+        if u64::from(page_number) > page_layout.page_count {
+            return Err(SQLiteInternalError::TruncatedFile(page_number));
+        }
+        db.seek(SeekFrom::Start(page_layout.page_offset(page_number)))
+            .map_err(SQLiteInternalError::SeekError)?;
+        db.read_exact(&mut page_number_bytes)
+            .map_err(SQLiteInternalError::ReadError)?;
+        db.read_exact(chunk)
+            .map_err(SQLiteInternalError::ReadError)?;
+    }
+
+    Ok(payload)
+}
+
+/// Walk the index b-tree rooted at the given page, and decode the key record of every entry, in
+/// index order.
+///
+/// Unlike table b-trees, both interior and leaf index pages hold entries:
+/// - leaf index page (0x0a): cells hold a payload size (varint) and the payload
+/// - interior index page (0x02): cells hold a 4-byte left child page number, then a payload size
+///   (varint) and the payload. The cell's entry sorts after every entry of its left subtree.
+///
+/// An index record holds the indexed column values followed by the rowid of the table row.
+pub(crate) fn index_records(
+    page_number: u32,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<Vec<Value>>, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+    let mut records = Vec::new();
+    match page_header.page_type {
+        PageType::LeafIndex => {
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                records.push(parse_record(
+                    &read_index_payload(offset, page_layout, db)?,
+                    encoding,
+                )?);
+            }
+        }
+        PageType::InteriorIndex => {
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                db.seek(SeekFrom::Start(offset))
+                    .map_err(SQLiteInternalError::SeekError)?;
+                let mut left_child_bytes = [0; 4];
+                db.read_exact(&mut left_child_bytes)
+                    .map_err(SQLiteInternalError::ReadError)?;
+                let left_child = u32::from_be_bytes(left_child_bytes);
+
+                records.extend(index_records(
+                    left_child,
+                    page_layout,
+                    encoding,
+                    page_guard,
+                    db,
+                )?);
+                records.push(parse_record(
+                    &read_index_payload(offset + 4, page_layout, db)?,
+                    encoding,
+                )?);
+            }
+            let rightmost_child = page_header
+                .rightmost_pointer
+                .expect("interior pages have a right-most pointer");
+            records.extend(index_records(
+                rightmost_child,
+                page_layout,
+                encoding,
+                page_guard,
+                db,
+            )?);
+        }
+        PageType::InteriorTable | PageType::LeafTable => {
+            return Err(SQLiteInternalError::UnexpectedPageType(
+                page_header.page_type,
+            ))
+        }
+    }
+    Ok(records)
+}
+
+/// Count the entries of the index b-tree rooted at the given page, without decoding them: every
+/// cell of its pages, interior ones included, is an entry.
+pub(crate) fn index_entry_count(
+    page_number: u32,
+    page_layout: PageLayout,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<usize, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+
+    match page_header.page_type {
+        PageType::LeafIndex => Ok(usize::from(page_header.nb_cells)),
+        PageType::InteriorIndex => {
+            let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+            let mut nb_entries = cell_ptr_array.len();
+            for cell_offset in cell_ptr_array {
+                db.seek(SeekFrom::Start(page_offset + u64::from(cell_offset)))
+                    .map_err(SQLiteInternalError::SeekError)?;
+                let mut left_child_bytes = [0; 4];
+                db.read_exact(&mut left_child_bytes)
+                    .map_err(SQLiteInternalError::ReadError)?;
+                let left_child = u32::from_be_bytes(left_child_bytes);
+                nb_entries += index_entry_count(left_child, page_layout, page_guard, db)?;
+            }
+            let rightmost_child = page_header
+                .rightmost_pointer
+                .expect("interior pages have a right-most pointer");
+            nb_entries += index_entry_count(rightmost_child, page_layout, page_guard, db)?;
+            Ok(nb_entries)
+        }
+        PageType::InteriorTable | PageType::LeafTable => Err(
+            SQLiteInternalError::UnexpectedPageType(page_header.page_type),
+        ),
+    }
+}
+
+/// Seek the index b-tree rooted at the given page for the records whose leading columns equal
+/// `key`, in index order. `key` may be shorter than the index records: a prefix of the index
+/// columns.
+///
+/// Only the subtrees that may hold matching records are visited.
+pub(crate) fn index_seek(
+    page_number: u32,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+    key: &[Value],
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<Vec<Value>>, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+    let mut records = Vec::new();
+    match page_header.page_type {
+        PageType::LeafIndex => {
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                let record = parse_record(&read_index_payload(offset, page_layout, db)?, encoding)?;
+                if compare_key_prefix(&record, key) == Ordering::Equal {
+                    records.push(record);
+                }
+            }
+        }
+        PageType::InteriorIndex => {
+            // Records of a left child sort before the cell's own record, and after the previous
+            // cell's record: past the first cell sorting after the key, no match is left.
+            for cell_offset in cell_ptr_array {
+                let offset = page_offset + u64::from(cell_offset);
+                db.seek(SeekFrom::Start(offset))
+                    .map_err(SQLiteInternalError::SeekError)?;
+                let mut left_child_bytes = [0; 4];
+                db.read_exact(&mut left_child_bytes)
+                    .map_err(SQLiteInternalError::ReadError)?;
+                let left_child = u32::from_be_bytes(left_child_bytes);
+
+                let record =
+                    parse_record(&read_index_payload(offset + 4, page_layout, db)?, encoding)?;
+                let ordering = compare_key_prefix(&record, key);
+                if ordering.is_ge() {
+                    records.extend(index_seek(
+                        left_child,
+                        page_layout,
+                        encoding,
+                        key,
+                        page_guard,
+                        db,
+                    )?);
+                }
+                match ordering {
+                    Ordering::Equal => records.push(record),
+                    Ordering::Greater => return Ok(records),
+                    Ordering::Less => {}
+                }
+            }
+            let rightmost_child = page_header
+                .rightmost_pointer
+                .expect("interior pages have a right-most pointer");
+            records.extend(index_seek(
+                rightmost_child,
+                page_layout,
+                encoding,
+                key,
+                page_guard,
+                db,
+            )?);
+        }
+        PageType::InteriorTable | PageType::LeafTable => {
+            return Err(SQLiteInternalError::UnexpectedPageType(
+                page_header.page_type,
+            ))
+        }
+    }
+    Ok(records)
+}
+
+/// Compare the leading columns of an index record with a key.
+fn compare_key_prefix(record: &[Value], key: &[Value]) -> Ordering {
+    record
+        .iter()
+        .zip(key)
+        .map(|(a, b)| compare_values(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Seek the table b-tree rooted at the given page for the row with the given rowid. Returns the
+/// location of its leaf cell, as `(page offset, cell offset)`.
+///
+/// Only one page is visited per level of the b-tree, and each page is binary searched: this is
+/// O(log n) in the number of rows.
+pub(crate) fn table_leaf_cell_by_rowid(
+    page_number: u32,
+    page_layout: PageLayout,
+    rowid: i64,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<Option<(u64, u16)>, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+    // Cells are sorted by rowid: binary search the first cell whose rowid is at least the target
+    let cell_rowid = |cell_offset: u16, db: &mut _| {
+        let offset = page_offset + u64::from(cell_offset);
+        let rowid_offset = match page_header.page_type {
+            // On leaf pages, the rowid follows the payload size
+            PageType::LeafTable => offset + parse_varint(offset, db)?.1 as u64,
+            // On interior pages, it follows the left child page number
+            _ => offset + 4,
+        };
+        Ok::<_, SQLiteInternalError>(parse_varint(rowid_offset, db)?.0 as i64)
+    };
+    let (mut low, mut high) = (0, cell_ptr_array.len());
+    while low < high {
+        let middle = (low + high) / 2;
+        if cell_rowid(cell_ptr_array[middle], db)? < rowid {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    let found_cell = cell_ptr_array.get(low).copied();
+
+    match page_header.page_type {
+        PageType::LeafTable => match found_cell {
+            Some(cell_offset) if cell_rowid(cell_offset, db)? == rowid => {
+                Ok(Some((page_offset, cell_offset)))
+            }
+            _ => Ok(None),
+        },
+        PageType::InteriorTable => {
+            // The left child of a cell holds the rowids up to and including the cell's key, the
+            // right-most child the rowids after the last key
+            let child_page = match found_cell {
+                Some(cell_offset) => {
+                    db.seek(SeekFrom::Start(page_offset + u64::from(cell_offset)))
+                        .map_err(SQLiteInternalError::SeekError)?;
+                    let mut left_child_bytes = [0; 4];
+                    db.read_exact(&mut left_child_bytes)
+                        .map_err(SQLiteInternalError::ReadError)?;
+                    u32::from_be_bytes(left_child_bytes)
+                }
+                None => page_header
+                    .rightmost_pointer
+                    .expect("interior pages have a right-most pointer"),
+            };
+            table_leaf_cell_by_rowid(child_page, page_layout, rowid, page_guard, db)
+        }
+        PageType::InteriorIndex | PageType::LeafIndex => Err(
+            SQLiteInternalError::UnexpectedPageType(page_header.page_type),
+        ),
+    }
+}
+
+/// Count the levels of the b-tree rooted at the given page, a single leaf page being 1 level.
+///
+/// Every leaf of a b-tree is at the same depth: the walk descends along the left-most children
+/// only, from the root page down to a leaf.
+pub(crate) fn btree_depth(
+    page_number: u32,
+    page_layout: PageLayout,
+    page_guard: &mut PageGuard,
+    db: &mut (impl Read + Seek),
+) -> Result<u32, SQLiteInternalError> {
+    let (page_offset, page_header) = read_btree_page(page_number, page_layout, page_guard, db)?;
+    if page_header.page_type.is_leaf() {
+        return Ok(1);
+    }
+
+    // Interior table and index cells both start with the 4-byte left child page number. A page
+    // with no cells only has its right-most child.
+    let cell_ptr_array = get_cell_ptr_array(page_header.nb_cells, db)?;
+    let child_page = match cell_ptr_array.first() {
+        Some(&cell_offset) => {
+            db.seek(SeekFrom::Start(page_offset + u64::from(cell_offset)))
+                .map_err(SQLiteInternalError::SeekError)?;
+            let mut left_child_bytes = [0; 4];
+            db.read_exact(&mut left_child_bytes)
+                .map_err(SQLiteInternalError::ReadError)?;
+            u32::from_be_bytes(left_child_bytes)
+        }
+        None => page_header
+            .rightmost_pointer
+            .expect("interior pages have a right-most pointer"),
+    };
+    Ok(1 + btree_depth(child_page, page_layout, page_guard, db)?)
+}
+
+/// Read the payload of an index cell, given the offset of its payload size varint.
+fn read_index_payload(
+    offset: u64,
+    page_layout: PageLayout,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<u8>, SQLiteInternalError> {
+    let (payload_size, payload_size_varint) = parse_varint(offset, db)?;
+
+    // 'X is ((U-12)*64/255)-23 for index pages'
+    let max_local = (page_layout.usable_size - 12) * 64 / 255 - 23;
+    read_payload(
+        offset + payload_size_varint as u64,
+        payload_size,
+        max_local,
+        page_layout,
+        db,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::{fixture_bytes, fixture_path, open_fixture, query_rows};
+    use crate::Database;
+
+    #[test]
+    fn cell_pointers_of_an_interior_page_follow_its_12_byte_header() {
+        let mut file = File::open(fixture_path("pages.db")).unwrap();
+        let page_layout = PageLayout::read(&mut file).unwrap();
+        let page_count = file.seek(SeekFrom::End(0)).unwrap() / page_layout.page_size;
+
+        // The root page 2 of 'numbers'
+        let page_offset = page_layout.seek_btree_page(2, &mut file).unwrap();
+        let page_header = read_page_header(&mut file).unwrap();
+        assert_eq!(page_header.page_type, PageType::InteriorTable);
+        let cell_offsets = get_cell_ptr_array(page_header.nb_cells, &mut file).unwrap();
+        assert_eq!(cell_offsets.len(), 62);
+
+        // Each cell starts with the 4-byte page number of its left child, a leaf of the table
+        for cell_offset in cell_offsets {
+            file.seek(SeekFrom::Start(page_offset + u64::from(cell_offset)))
+                .unwrap();
+            let mut left_child = [0; 4];
+            file.read_exact(&mut left_child).unwrap();
+            let left_child = u64::from(u32::from_be_bytes(left_child));
+            assert!((3..=page_count).contains(&left_child));
+        }
+    }
+
+    #[test]
+    fn auto_vacuum_database_skips_pointer_map_pages() {
+        let mut file = File::open(fixture_path("autovacuum.db")).unwrap();
+        let page_layout = PageLayout::read(&mut file).unwrap();
+        assert!(page_layout.is_ptrmap_page(2));
+        assert!(page_layout.is_ptrmap_page(105));
+        assert!(!page_layout.is_ptrmap_page(3));
+        assert!(matches!(
+            page_layout.seek_btree_page(105, &mut file),
+            Err(SQLiteInternalError::UnexpectedPtrmapPage(105))
+        ));
+    }
+
+    #[test]
+    fn child_pointer_to_its_own_page_is_a_cycle() {
+        // Point the first cell of the interior root page 2 of 'numbers' back to page 2
+        let mut bytes = fixture_bytes("pages.db");
+        let page_offset = 512;
+        let cell_ptr_array = page_offset + 12;
+        let cell_offset = usize::from(u16::from_be_bytes([
+            bytes[cell_ptr_array],
+            bytes[cell_ptr_array + 1],
+        ]));
+        bytes[page_offset + cell_offset..page_offset + cell_offset + 4]
+            .copy_from_slice(&2u32.to_be_bytes());
+
+        let mut database = Database::new(Cursor::new(bytes));
+        assert!(matches!(
+            database.table_leaf_cells(2),
+            Err(SQLiteInternalError::PageCycle(2))
+        ));
+    }
+
+    #[test]
+    fn traversal_stops_at_the_page_limit() {
+        // 'numbers' spans 64 pages
+        let mut database = open_fixture("pages.db").with_max_pages(10);
+        assert!(matches!(
+            database.table_leaf_cells(2),
+            Err(SQLiteInternalError::TooManyPages(10))
+        ));
+    }
+
+    #[test]
+    fn page_info_walks_the_freeblock_of_a_deleted_row() {
+        // Row 2 of 'notes' was deleted: its 16-byte cell, between rows 1 and 3, is a freeblock
+        let mut database = open_fixture("freeblocks.db");
+        let page_info = database.page_info(2).unwrap();
+        assert_eq!(page_info.nb_cells, 2);
+        assert_eq!(page_info.freeblocks, vec![(481, 16)]);
+        assert_eq!(page_info.fragmented_free_bytes, 0);
+        assert_eq!(page_info.free_bytes(), page_info.unallocated_bytes + 16);
+        let ids = database
+            .table("notes")
+            .unwrap()
+            .column_values("id")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ids, vec![Value::Integer(1), Value::Integer(3)]);
+    }
+
+    #[test]
+    fn truncated_file_names_the_missing_page() {
+        // Keep the first 10 of the 64 pages of 'numbers'
+        let mut bytes = fixture_bytes("pages.db");
+        bytes.truncate(10 * 512);
+        let mut database = Database::new(Cursor::new(bytes));
+        match database.table_leaf_cells(2) {
+            Err(SQLiteInternalError::TruncatedFile(page_number)) => assert!(page_number > 10),
+            result => panic!("expected a truncated file error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn payload_of_exactly_the_maximum_local_size_has_no_overflow_page() {
+        let mut database = open_fixture("payloads.db");
+        // The only cell of 'exact_fit' ends the file: reading an overflow page number after its
+        // payload would fail
+        assert_eq!(database.root_page("exact_fit").unwrap(), 4);
+        assert_eq!(database.page_count().unwrap().count, 4);
+        let page_info = database.page_info(4).unwrap();
+        assert_eq!(page_info.nb_cells, 1);
+        assert_eq!(page_info.free_bytes(), 512 - 8 - 2 - (2 + 1 + 477));
+
+        let x = |len: usize| Value::Text("x".repeat(len));
+        for (table_name, len) in [("exact_fit", 473), ("spilled", 474)] {
+            let bodies = database
+                .table(table_name)
+                .unwrap()
+                .column_values("body")
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(bodies, vec![x(len)]);
+        }
+    }
+
+    #[test]
+    fn btree_depth_counts_the_levels_of_a_table() {
+        assert_eq!(open_fixture("sample.db").btree_depth("apples").unwrap(), 1);
+        // An interior root page above the leaves
+        assert_eq!(open_fixture("pages.db").btree_depth("numbers").unwrap(), 2);
+    }
+
+    #[test]
+    fn page_number_past_the_u16_range() {
+        // 'numbers' of 'pages.db', whose last leaf is moved to page 70000: the root page 2 points
+        // to it in its right-most pointer, at offset 8 of its header
+        const PAGE_SIZE: usize = 512;
+        const LAST_LEAF: u32 = 70_000;
+        let mut bytes = fixture_bytes("pages.db");
+        let right_most_pointer = PAGE_SIZE + 8..PAGE_SIZE + 12;
+        let moved_page = u32::from_be_bytes(bytes[right_most_pointer.clone()].try_into().unwrap());
+        let moved_page_offset = (moved_page as usize - 1) * PAGE_SIZE;
+        let leaf = bytes[moved_page_offset..moved_page_offset + PAGE_SIZE].to_vec();
+        bytes.resize((LAST_LEAF as usize - 1) * PAGE_SIZE, 0);
+        bytes.extend_from_slice(&leaf);
+        bytes[right_most_pointer].copy_from_slice(&LAST_LEAF.to_be_bytes());
+        bytes[28..32].copy_from_slice(&LAST_LEAF.to_be_bytes());
+
+        let mut database = Database::from_bytes(bytes).unwrap();
+        assert_eq!(database.page_count().unwrap().count, u64::from(LAST_LEAF));
+        assert_eq!(
+            database.page_info(LAST_LEAF).unwrap().page_type,
+            PageType::LeafTable
+        );
+        assert_eq!(
+            query_rows(&mut database, "SELECT label FROM numbers WHERE id = 1000"),
+            vec![vec![Value::Text("number 1000 of the table".to_string())]]
+        );
+        assert_eq!(
+            query_rows(&mut database, "SELECT COUNT(*) FROM numbers"),
+            vec![vec![Value::Integer(1000)]]
+        );
+    }
+
+    #[test]
+    fn cells_stored_out_of_cell_pointer_order() {
+        let mut db = File::open(fixture_path("shuffled.db")).unwrap();
+        let page_layout = PageLayout::read(&mut db).unwrap();
+        page_layout.seek_btree_page(2, &mut db).unwrap();
+        let page_header = read_page_header(&mut db).unwrap();
+        let cell_offsets = get_cell_ptr_array(page_header.nb_cells, &mut db).unwrap();
+        // Rows 1, 2 and 3, whose cells are not laid out in that order
+        assert_eq!(cell_offsets.len(), 3);
+        assert!(cell_offsets[1] < cell_offsets[2] && cell_offsets[2] < cell_offsets[0]);
+
+        let mut database = open_fixture("shuffled.db");
+        assert_eq!(
+            query_rows(&mut database, "SELECT id, letter FROM letters"),
+            vec![
+                vec![Value::Integer(1), Value::Text("a".to_string())],
+                vec![Value::Integer(2), Value::Text("b".to_string())],
+                vec![Value::Integer(3), Value::Text("c".to_string())],
+            ]
+        );
+        let page = database.page(2).unwrap();
+        let letters = page
+            .records()
+            .map(|record| record.unwrap()[1].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            letters,
+            ["a", "b", "c"].map(|letter| Value::Text(letter.to_string()))
+        );
+    }
+}
@@ -0,0 +1,765 @@
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Cursor};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::btree::{
+    btree_depth, index_entry_count, index_records, index_seek, read_page_info,
+    table_leaf_cell_by_rowid, table_leaf_cells, table_leaf_cells_in_range, PageGuard, PageInfo,
+    PageLayout,
+};
+use crate::exec::{handle_sql_query, run_sql_query, ResultSet};
+use crate::header::DbHeader;
+use crate::page::Page;
+use crate::query::parse_sql_query_with_parameters;
+use crate::schema::{find_table, parse_schema_table, ObjectType, SchemaRow, Stat1Row};
+use crate::table::Table;
+use crate::value::Value;
+use crate::{
+    OutputConfig, SQLQuery, SQLQueryError, SQLiteError, SQLiteInternalError, TextEncoding,
+};
+
+/// Size of the database header, at the start of the first page.
+pub(crate) const DB_HEADER_SIZE: u64 = 100;
+
+/// Root page of the schema table.
+const SCHEMA_ROOT_PAGE: u32 = 1;
+
+/// Root page recorded in the schema for objects without a b-tree, e.g. views. Page numbers start
+/// at 1: such an object is read as empty.
+const NO_ROOT_PAGE: u32 = 0;
+
+/// A SQLite database, read from any seekable source (usually a file).
+#[derive(Debug)]
+pub struct Database<R: Read + Seek> {
+    pub(crate) reader: R,
+    /// Maximum number of pages a single b-tree traversal may visit. Defaults to the number of
+    /// pages in the file.
+    max_pages: Option<u64>,
+    /// Whether to log every b-tree page read to stderr.
+    trace: bool,
+    /// Text encoding to decode text values with, instead of the one recorded in the header.
+    encoding: Option<TextEncoding>,
+    /// Whether queries print how they read each table, before their results.
+    pub(crate) explain: bool,
+}
+
+impl Database<BufReader<File>> {
+    /// Open a database file. Reads are buffered, see `with_buffer_size`.
+    ///
+    /// The path is used as is, e.g. with spaces or non-ASCII characters: the error names it if the
+    /// file cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SQLiteError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|source| SQLiteError::CantOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Database::new(BufReader::new(file)))
+    }
+}
+
+impl Database<Cursor<Vec<u8>>> {
+    /// Read a database from its bytes, e.g. downloaded or embedded, without touching the
+    /// filesystem. The database header is checked up front.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, SQLiteError> {
+        let mut reader = Cursor::new(bytes);
+        DbHeader::read(&mut reader)?;
+        Ok(Database::new(reader))
+    }
+}
+
+impl<'a> Database<Cursor<&'a [u8]>> {
+    /// Read a database from borrowed bytes, e.g. a read-only memory map of the file, which must
+    /// outlive the database. Unlike `from_bytes`, the file is not copied: pages are read straight
+    /// from the slice. The database header is checked up front.
+    ///
+    /// Values read from rows are still owned, e.g. a blob is copied out of the slice when its row is
+    /// read. `Table::column_bytes` borrows the bytes of a text or blob value from the slice instead.
+    pub fn from_slice(bytes: &'a [u8]) -> Result<Self, SQLiteError> {
+        let mut reader = Cursor::new(bytes);
+        DbHeader::read(&mut reader)?;
+        Ok(Database::new(reader))
+    }
+}
+
+impl<R: Read + Seek> Database<BufReader<R>> {
+    /// Set the capacity of the read buffer, in bytes. The default is 8 KiB.
+    ///
+    /// Records and b-tree pages are read in many small reads after each seek, e.g. one per byte of
+    /// a varint: the buffer serves them with a single read from the underlying source. Every seek
+    /// discards the buffer though, so a buffer much larger than a page only reads bytes that are
+    /// dropped. A buffer of a page holds a whole page header and cell pointer array.
+    pub fn with_buffer_size(mut self, capacity: usize) -> Self {
+        // Every read seeks to an absolute offset first: the buffered bytes can be dropped
+        self.reader = BufReader::with_capacity(capacity, self.reader.into_inner());
+        self
+    }
+}
+
+impl<R: Read + Seek> Database<R> {
+    pub fn new(reader: R) -> Self {
+        Database {
+            reader,
+            max_pages: None,
+            trace: false,
+            encoding: None,
+            explain: false,
+        }
+    }
+
+    /// Cap the number of pages a b-tree traversal may visit, to stop early on corrupt files.
+    pub fn with_max_pages(mut self, max_pages: u64) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Log every b-tree page read to stderr, with its type and cell count.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Print the plan of each query to stderr before its results: how the rows of each table are
+    /// read, e.g. 'SEARCH apples USING INDEX idx_color (color=?)'.
+    pub fn with_explain(mut self) -> Self {
+        self.explain = true;
+        self
+    }
+
+    /// Decode text values with the given encoding, whatever the header records. This is meant to
+    /// recover databases whose header is wrong.
+    pub fn with_encoding(mut self, encoding: TextEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Run a query, printing its results to stdout.
+    pub fn execute(
+        &mut self,
+        sql_query: &SQLQuery,
+        output_config: &OutputConfig,
+    ) -> Result<(), SQLQueryError> {
+        handle_sql_query(sql_query, self, output_config)
+    }
+
+    /// Run a query holding `?` placeholders, bound to the given parameters in order, and return
+    /// its output: its columns, with their declared types, and the values of its rows.
+    ///
+    /// Example: `database.query("SELECT name FROM apples WHERE color = ?", &[Value::Text("Red".to_string())])`
+    pub fn query(
+        &mut self,
+        sql_query: &str,
+        parameters: &[Value],
+    ) -> Result<ResultSet, SQLiteError> {
+        let sql_query = parse_sql_query_with_parameters(sql_query, parameters)?;
+        Ok(run_sql_query(&sql_query, self)?)
+    }
+
+    /// Number of pages in the database, derived from the file size.
+    ///
+    /// The header records the page count too, at offset 28, but it is only trusted by SQLite when
+    /// the 'version-valid-for' number at offset 92 matches the change counter at offset 24: older
+    /// versions of SQLite did not keep it up to date. A valid but different in-header count is
+    /// returned along with the count, for the caller to warn about, see `PageCount::warning`.
+    pub fn page_count(&mut self) -> Result<PageCount, SQLiteError> {
+        let count = PageLayout::read(&mut self.reader)?.page_count;
+
+        let db_header = DbHeader::read(&mut self.reader)?;
+        let header_mismatch = Some(db_header.page_count).filter(|&header_count| {
+            db_header.is_page_count_valid() && u64::from(header_count) != count
+        });
+
+        Ok(PageCount {
+            count,
+            header_mismatch,
+        })
+    }
+
+    /// The database header.
+    pub fn header(&mut self) -> Result<DbHeader, SQLiteError> {
+        Ok(DbHeader::read(&mut self.reader)?)
+    }
+
+    /// The text encoding text values are decoded with: the one set with `with_encoding`, else the
+    /// one recorded in the header. An unknown value in the header is read as UTF-8.
+    pub fn encoding(&mut self) -> Result<TextEncoding, SQLiteError> {
+        Ok(self.text_encoding()?)
+    }
+
+    /// Space usage of a b-tree page: free space and cell count.
+    pub fn page_info(&mut self, page_number: u32) -> Result<PageInfo, SQLiteError> {
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        Ok(read_page_info(page_number, page_layout, &mut self.reader)?)
+    }
+
+    /// Read a b-tree page whole, e.g. to decode the rows of a leaf table page with
+    /// `Page::records`.
+    pub fn page(&mut self, page_number: u32) -> Result<Page, SQLiteError> {
+        let encoding = self.text_encoding()?;
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        Ok(Page::read(
+            page_number,
+            page_layout,
+            encoding,
+            &mut self.reader,
+        )?)
+    }
+
+    /// Number of tables in the schema, SQLite internal tables like 'sqlite_sequence' included.
+    pub fn table_count(&mut self) -> Result<usize, SQLiteError> {
+        Ok(self
+            .schema_table_rows()?
+            .iter()
+            .filter(|row| row.object_type == ObjectType::Table)
+            .count())
+    }
+
+    /// Names of the user tables, in schema order.
+    ///
+    /// Indexes, views, triggers and SQLite internal tables (e.g. 'sqlite_sequence') are left out.
+    pub fn table_names(&mut self) -> Result<Vec<String>, SQLiteError> {
+        self.schema_object_names(ObjectType::Table)
+    }
+
+    /// Names of the views, in schema order.
+    pub fn view_names(&mut self) -> Result<Vec<String>, SQLiteError> {
+        self.schema_object_names(ObjectType::View)
+    }
+
+    /// Number of rows of each user table, in schema order, SQLite internal tables left out. Rows
+    /// are counted from the cells of the table b-trees, without decoding them.
+    pub fn row_counts(&mut self) -> Result<Vec<(String, usize)>, SQLiteError> {
+        let mut row_counts = Vec::new();
+        for row in self.schema_table_rows()? {
+            if row.object_type == ObjectType::Table && !row.name.starts_with("sqlite_") {
+                let nb_rows = self.table_leaf_cells(row.root_page)?.len();
+                row_counts.push((row.name, nb_rows));
+            }
+        }
+        Ok(row_counts)
+    }
+
+    /// Page number of the root page of a table's b-tree, as recorded in the schema.
+    pub fn root_page(&mut self, table_name: &str) -> Result<u32, SQLiteError> {
+        Ok(self.table(table_name)?.root_page())
+    }
+
+    /// Number of levels of a table's b-tree: 1 when the whole table fits in its root page. A
+    /// lookup by rowid reads one page per level.
+    pub fn btree_depth(&mut self, table_name: &str) -> Result<u32, SQLiteError> {
+        let root_page = self.root_page(table_name)?;
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        Ok(btree_depth(
+            root_page,
+            page_layout,
+            &mut page_guard,
+            &mut self.reader,
+        )?)
+    }
+
+    /// The CREATE statement of a table, index, view or trigger, as stored in the schema.
+    ///
+    /// Returns `None` if there is no such object, or if it has no CREATE statement, like the
+    /// indexes SQLite creates automatically for UNIQUE and PRIMARY KEY constraints.
+    pub fn table_sql(&mut self, name: &str) -> Result<Option<String>, SQLiteError> {
+        let schema_rows = self.schema_table_rows()?;
+        let schema_row = find_table(&schema_rows, name)
+            .or_else(|| schema_rows.into_iter().find(|row| row.name == name));
+        Ok(schema_row.map(|row| row.sql).filter(|sql| !sql.is_empty()))
+    }
+
+    /// Look up a table by name.
+    pub fn table(&mut self, table_name: &str) -> Result<Table<'_, R>, SQLiteError> {
+        let schema_rows = self.schema_table_rows()?;
+        let schema_row = find_table(&schema_rows, table_name)
+            .ok_or_else(|| SQLQueryError::InvalidSQL(format!("no such table: {table_name}")))?;
+        Table::new(self, &schema_row)
+    }
+
+    /// The rows of the schema table, i.e. one per table, index, view and trigger, in schema order.
+    ///
+    /// See the schema table doc: https://www.sqlite.org/schematab.html
+    pub fn schema_rows(&mut self) -> Result<Vec<SchemaRow>, SQLiteError> {
+        Ok(self.schema_table_rows()?)
+    }
+
+    /// The statistics ANALYZE collected in the 'sqlite_stat1' table, in table order, or nothing if
+    /// the database was never analyzed.
+    pub fn stat1_rows(&mut self) -> Result<Vec<Stat1Row>, SQLiteError> {
+        let has_stat1 = self
+            .schema_table_rows()?
+            .iter()
+            .any(|row| row.object_type == ObjectType::Table && row.name == "sqlite_stat1");
+        if !has_stat1 {
+            return Ok(Vec::new());
+        }
+
+        let as_text = |value: &Value| match value {
+            Value::Null => None,
+            value => Some(value.to_string()),
+        };
+        self.table("sqlite_stat1")?
+            .rows()
+            .map(|row| {
+                let row = row?;
+                match row.values.as_slice() {
+                    [tbl, idx, stat] => Ok(Stat1Row {
+                        tbl: as_text(tbl).unwrap_or_default(),
+                        idx: as_text(idx),
+                        stat: as_text(stat).unwrap_or_default(),
+                    }),
+                    _ => Err(SQLiteInternalError::MalformedRecord.into()),
+                }
+            })
+            .collect()
+    }
+
+    /// Read the rows of the schema table, after checking the database header.
+    pub(crate) fn schema_table_rows(&mut self) -> Result<Vec<SchemaRow>, SQLiteInternalError> {
+        let encoding = self.text_encoding()?;
+
+        // The schema table is a table b-tree rooted at page 1: with enough schema objects, it
+        // spans several pages
+        let leaf_cells = self.table_leaf_cells(SCHEMA_ROOT_PAGE)?;
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        parse_schema_table(leaf_cells, page_layout, encoding, &mut self.reader)
+    }
+
+    /// See `encoding`.
+    pub(crate) fn text_encoding(&mut self) -> Result<TextEncoding, SQLiteInternalError> {
+        match self.encoding {
+            Some(encoding) => Ok(encoding),
+            None => Ok(DbHeader::read(&mut self.reader)?
+                .encoding()
+                .unwrap_or_default()),
+        }
+    }
+
+    /// Locate the cells of every row of the table b-tree rooted at the given page.
+    pub(crate) fn table_leaf_cells(
+        &mut self,
+        root_page: u32,
+    ) -> Result<Vec<(u64, u16)>, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE {
+            return Ok(Vec::new());
+        }
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        table_leaf_cells(root_page, page_layout, &mut page_guard, &mut self.reader)
+    }
+
+    /// Locate the cells of the rows whose rowid is in the given range, in the table b-tree rooted
+    /// at the given page.
+    pub(crate) fn table_leaf_cells_in_range(
+        &mut self,
+        root_page: u32,
+        rowids: &RangeInclusive<i64>,
+    ) -> Result<Vec<(u64, u16)>, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE || rowids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        table_leaf_cells_in_range(
+            root_page,
+            page_layout,
+            rowids,
+            &mut page_guard,
+            &mut self.reader,
+        )
+    }
+
+    /// Decode the key record of every entry of the index b-tree rooted at the given page.
+    pub(crate) fn index_records(
+        &mut self,
+        root_page: u32,
+    ) -> Result<Vec<Vec<Value>>, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE {
+            return Ok(Vec::new());
+        }
+        let encoding = self.text_encoding()?;
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        index_records(
+            root_page,
+            page_layout,
+            encoding,
+            &mut page_guard,
+            &mut self.reader,
+        )
+    }
+
+    /// Count the entries of the index b-tree rooted at the given page.
+    pub(crate) fn index_entry_count(
+        &mut self,
+        root_page: u32,
+    ) -> Result<usize, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE {
+            return Ok(0);
+        }
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        index_entry_count(root_page, page_layout, &mut page_guard, &mut self.reader)
+    }
+
+    /// Seek the index b-tree rooted at the given page for the records whose leading columns equal
+    /// `key`.
+    pub(crate) fn index_seek(
+        &mut self,
+        root_page: u32,
+        key: &[Value],
+    ) -> Result<Vec<Vec<Value>>, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE {
+            return Ok(Vec::new());
+        }
+        let encoding = self.text_encoding()?;
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        index_seek(
+            root_page,
+            page_layout,
+            encoding,
+            key,
+            &mut page_guard,
+            &mut self.reader,
+        )
+    }
+
+    /// Locate the cell of the row with the given rowid in the table b-tree rooted at the given page.
+    pub(crate) fn table_leaf_cell_by_rowid(
+        &mut self,
+        root_page: u32,
+        rowid: i64,
+    ) -> Result<Option<(u64, u16)>, SQLiteInternalError> {
+        if root_page == NO_ROOT_PAGE {
+            return Ok(None);
+        }
+        let page_layout = PageLayout::read(&mut self.reader)?;
+        let mut page_guard = self.page_guard(&page_layout);
+        table_leaf_cell_by_rowid(
+            root_page,
+            page_layout,
+            rowid,
+            &mut page_guard,
+            &mut self.reader,
+        )
+    }
+
+    fn schema_object_names(&mut self, object_type: ObjectType) -> Result<Vec<String>, SQLiteError> {
+        Ok(self
+            .schema_table_rows()?
+            .into_iter()
+            .filter(|row| row.object_type == object_type)
+            .filter(|row| !row.name.starts_with("sqlite_"))
+            .map(|row| row.name)
+            .collect())
+    }
+
+    fn page_guard(&self, page_layout: &PageLayout) -> PageGuard {
+        PageGuard::new(self.max_pages.unwrap_or(page_layout.page_count), self.trace)
+    }
+}
+
+/// Number of pages of a database, see `Database::page_count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCount {
+    /// Number of pages, derived from the file size.
+    pub count: u64,
+    /// The in-header page count, if it is valid but differs from `count`: the file size wins.
+    pub header_mismatch: Option<u32>,
+}
+
+impl PageCount {
+    /// The warning for a stale in-header page count, if any.
+    pub fn warning(&self) -> Option<String> {
+        self.header_mismatch.map(|header_count| {
+            format!(
+                "warning: the header records {header_count} pages, but the file holds {}",
+                self.count
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::{fixture_bytes, fixture_path, open_fixture, query_rows};
+    use crate::PageType;
+
+    #[test]
+    fn table_names_leave_out_internal_tables() {
+        let mut database = open_fixture("sample.db");
+        // 'sqlite_sequence' is left out
+        assert_eq!(database.table_names().unwrap(), vec!["apples", "oranges"]);
+    }
+
+    #[test]
+    fn table_names_leave_out_views() {
+        let mut database = open_fixture("views.db");
+        assert_eq!(database.table_names().unwrap(), vec!["fruits", "orders"]);
+        assert_eq!(
+            database.view_names().unwrap(),
+            vec!["red_fruits", "fruit_orders"]
+        );
+    }
+
+    #[test]
+    fn table_sql_is_the_stored_create_statement() {
+        let mut database = open_fixture("sample.db");
+        assert_eq!(
+            database.table_sql("apples").unwrap().as_deref(),
+            Some("CREATE TABLE apples\n(\n\tid integer primary key autoincrement,\n\tname text,\n\tcolor text\n)")
+        );
+        assert_eq!(database.table_sql("nope").unwrap(), None);
+
+        let mut database = open_fixture("misc.db");
+        assert_eq!(
+            database.table_sql("idx_fruits_color").unwrap().as_deref(),
+            Some("CREATE INDEX idx_fruits_color ON fruits (color)")
+        );
+    }
+
+    #[test]
+    fn encoding_override_decodes_a_mislabeled_database() {
+        fn greetings(database: &mut Database<impl Read + Seek>) -> Result<Vec<Value>, SQLiteError> {
+            database
+                .table("greetings")?
+                .column_values("greeting")
+                .collect()
+        }
+        let expected_greetings = ["hello", "héllo wörld", "日本語"]
+            .map(|greeting| Value::Text(greeting.to_string()))
+            .to_vec();
+        let mut database = open_fixture("utf16le.db");
+        assert_eq!(database.encoding().unwrap(), TextEncoding::Utf16le);
+        assert_eq!(greetings(&mut database).unwrap(), expected_greetings);
+
+        // Label the database as UTF-8: even its schema cannot be read as such
+        let mut bytes = fixture_bytes("utf16le.db");
+        bytes[56..60].copy_from_slice(&1u32.to_be_bytes());
+        let mut database = Database::new(Cursor::new(bytes.clone()));
+        assert_eq!(
+            database.header().unwrap().encoding(),
+            Some(TextEncoding::Utf8)
+        );
+        assert!(greetings(&mut database).is_err());
+
+        let mut database = Database::new(Cursor::new(bytes)).with_encoding(TextEncoding::Utf16le);
+        assert_eq!(greetings(&mut database).unwrap(), expected_greetings);
+    }
+
+    #[test]
+    fn database_is_read_from_bytes_in_memory() {
+        let mut database = Database::from_bytes(fixture_bytes("sample.db")).unwrap();
+        assert_eq!(database.table_names().unwrap(), vec!["apples", "oranges"]);
+        let row = database
+            .table("apples")
+            .unwrap()
+            .row_by_rowid(2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.values[1], Value::Text("Fuji".to_string()));
+
+        // The header is checked up front
+        assert!(matches!(
+            Database::from_bytes(b"not a database".to_vec()),
+            Err(SQLiteError::InternalError(
+                SQLiteInternalError::NotADatabase
+            ))
+        ));
+    }
+
+    #[test]
+    fn large_blob_is_read_from_borrowed_bytes() {
+        let bytes = fixture_bytes("blobs.db");
+        let mut database = Database::from_slice(&bytes).unwrap();
+        let rows = query_rows(&mut database, "SELECT name, data FROM files WHERE id = 1");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0], Value::Text("zeros.bin".to_string()));
+        assert!(matches!(&rows[0][1], Value::Blob(blob) if blob.len() == 20000));
+    }
+
+    #[test]
+    fn row_counts_of_each_table() {
+        let row_counts = open_fixture("sample.db").row_counts().unwrap();
+        let row_counts = row_counts
+            .iter()
+            .map(|(table_name, nb_rows)| (table_name.as_str(), *nb_rows))
+            .collect::<Vec<_>>();
+        assert_eq!(row_counts, [("apples", 4), ("oranges", 6)]);
+
+        // 'numbers' spans 63 leaves
+        let row_counts = open_fixture("pages.db").row_counts().unwrap();
+        assert_eq!(row_counts, [("numbers".to_string(), 1000)]);
+    }
+
+    #[test]
+    fn schema_rows_expose_every_field() {
+        let schema_rows = open_fixture("views.db").schema_rows().unwrap();
+        let fields = schema_rows
+            .iter()
+            .map(|row| {
+                (
+                    row.object_type,
+                    row.name.as_str(),
+                    row.tbl_name.as_str(),
+                    row.root_page,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                (ObjectType::Table, "fruits", "fruits", 2),
+                (ObjectType::Table, "orders", "orders", 3),
+                (ObjectType::View, "red_fruits", "red_fruits", 0),
+                (ObjectType::View, "fruit_orders", "fruit_orders", 0),
+            ]
+        );
+        assert_eq!(
+            schema_rows[2].sql,
+            "CREATE VIEW red_fruits AS SELECT id, name FROM fruits WHERE color = 'red'"
+        );
+    }
+
+    #[test]
+    fn create_statement_spilling_onto_overflow_pages() {
+        // The 969-byte CREATE statement of 'wide' does not fit in a 512-byte page
+        let mut database = open_fixture("wide.db");
+        let columns = (1..=60)
+            .map(|n| format!("column_{n} TEXT"))
+            .collect::<Vec<_>>();
+        let sql = format!("CREATE TABLE wide ({})", columns.join(", "));
+        assert_eq!(sql.len(), 969);
+        assert_eq!(database.table_sql("wide").unwrap(), Some(sql));
+        assert_eq!(
+            query_rows(&mut database, "SELECT column_1, column_60 FROM wide"),
+            vec![vec![
+                Value::Text("first".to_string()),
+                Value::Text("last".to_string())
+            ]]
+        );
+    }
+
+    #[test]
+    fn stat1_rows_of_an_analyzed_database() {
+        assert_eq!(
+            open_fixture("analyzed.db").stat1_rows().unwrap(),
+            vec![Stat1Row {
+                tbl: "cities".to_string(),
+                idx: Some("idx_cities_country".to_string()),
+                // 6 rows, 2 per country on average
+                stat: "6 2".to_string(),
+            }]
+        );
+        assert_eq!(open_fixture("sample.db").stat1_rows().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn open_error_names_the_path() {
+        let path = fixture_path("missing database.db");
+        match Database::open(&path) {
+            Err(err @ SQLiteError::CantOpen { .. }) => {
+                assert!(err.to_string().contains(&path));
+            }
+            result => panic!("expected a CantOpen error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn schema_whose_root_page_1_is_an_interior_page() {
+        let mut database = open_fixture("many_tables.db");
+        assert_eq!(
+            database.page_info(1).unwrap().page_type,
+            PageType::InteriorTable
+        );
+
+        let schema_rows = database.schema_rows().unwrap();
+        let names = schema_rows
+            .iter()
+            .map(|row| row.name.clone())
+            .collect::<Vec<_>>();
+        let expected = (1..=40).map(|n| format!("table_{n}")).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+        // The last table is on the last leaf of the schema
+        assert_eq!(
+            query_rows(&mut database, "SELECT name FROM table_40"),
+            vec![vec![Value::Text("last".to_string())]]
+        );
+    }
+
+    #[test]
+    fn buffer_size_does_not_change_query_results() {
+        let labels = |database: &mut Database<BufReader<File>>| {
+            database
+                .table("numbers")
+                .unwrap()
+                .column_values("label")
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let expected = labels(&mut open_fixture("pages.db"));
+        assert_eq!(expected.len(), 1000);
+        for capacity in [1, 512, 64 * 1024] {
+            let mut database = open_fixture("pages.db").with_buffer_size(capacity);
+            assert_eq!(labels(&mut database), expected);
+            assert_eq!(database.reader.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn page_count_matching_the_header_has_no_warning() {
+        let mut database = open_fixture("sample.db");
+        let page_count = database.page_count().unwrap();
+        assert_eq!(
+            page_count,
+            PageCount {
+                count: 4,
+                header_mismatch: None
+            }
+        );
+        assert_eq!(page_count.warning(), None);
+        assert_eq!(database.header().unwrap().page_count, 4);
+    }
+
+    #[test]
+    fn stale_header_page_count_is_warned_about() {
+        let mut bytes = fixture_bytes("sample.db");
+        bytes[28..32].copy_from_slice(&9u32.to_be_bytes());
+        let mut database = Database::new(Cursor::new(bytes));
+        // The file size wins
+        let page_count = database.page_count().unwrap();
+        assert_eq!(
+            page_count,
+            PageCount {
+                count: 4,
+                header_mismatch: Some(9)
+            }
+        );
+        assert_eq!(
+            page_count.warning().as_deref(),
+            Some("warning: the header records 9 pages, but the file holds 4")
+        );
+    }
+
+    #[test]
+    fn table_with_root_page_0_is_empty() {
+        // Set the root page of 'oranges' (4) to 0 in its schema record
+        let mut bytes = fixture_bytes("sample.db");
+        let record_start = bytes
+            .windows(20)
+            .position(|window| window == b"tableorangesoranges\x04")
+            .unwrap();
+        bytes[record_start + 19] = 0;
+
+        let mut database = Database::new(Cursor::new(bytes));
+        assert_eq!(database.root_page("oranges").unwrap(), 0);
+        let mut table = database.table("oranges").unwrap();
+        assert_eq!(table.column_values("name").count(), 0);
+    }
+}
@@ -0,0 +1,121 @@
+use thiserror::Error;
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::btree::PageType;
+use crate::tokenizer::TokenizerError;
+
+#[derive(Error, Debug)]
+pub enum SQLiteError {
+    #[error("unable to open database \"{}\": {}", .path.display(), .source)]
+    CantOpen { path: PathBuf, source: io::Error },
+    #[error("I/O error: {}", .0)]
+    Io(#[from] io::Error), // e.g. reading commands from stdin
+    #[error("{}", .0)]
+    SQLiteQueryError(#[from] SQLQueryError),
+    #[error("Query parsing error: {}", .0)]
+    QueryParsingError(#[from] SQLQueryParsingError),
+    #[error("Internal error: {}", .0)]
+    InternalError(#[from] SQLiteInternalError),
+}
+
+#[derive(Debug, Error)]
+pub enum SQLQueryParsingError {
+    #[error("{}", .0)]
+    TokenizerError(#[from] TokenizerError),
+    #[error("expected {}, found '{}' at position {}", .expected, .found, .position)]
+    UnexpectedToken {
+        expected: &'static str,
+        found: String,
+        position: usize,
+    },
+    #[error("unknown schema '{}' at position {}: only 'main' can be queried", .schema, .position)]
+    UnknownSchema { schema: String, position: usize },
+    #[error("expected {}, found the end of the query", .0)]
+    UnexpectedEnd(&'static str),
+    #[error("Invalid literal: {}", .0)]
+    BadLiteral(String),
+    #[error("ESCAPE expression must be a single character, found '{}'", .0)]
+    InvalidEscape(String),
+    #[error("missing columns at position {}: expected a column, an expression or '*' between SELECT and FROM", .position)]
+    EmptySelectList { position: usize },
+    #[error("datatype mismatch: LIMIT expects an integer")]
+    NonIntegerLimit,
+    #[error("no such function: {}", .0)]
+    UnknownFunction(String),
+    #[error("the query has {} '?' placeholders, but {} parameters are bound", .nb_placeholders, .nb_parameters)]
+    WrongParameterCount {
+        nb_placeholders: usize,
+        nb_parameters: usize,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum SQLQueryError {
+    #[error("Invalid SQL query: {}", .0)]
+    InvalidSQL(String),
+    #[error("no such column: {} (in table {})", .column_name, .table_name)]
+    NoSuchColumn {
+        column_name: String,
+        table_name: String,
+    },
+    #[error("SQL query not implemented yet: {}", .0)]
+    NotImplementedYet(String),
+    #[error("Internal error: {}", .0)]
+    InternalError(#[from] SQLiteInternalError),
+}
+
+#[derive(Debug, Error)]
+pub enum SQLiteInternalError {
+    #[error("Could not seek db file from start to offset: {:?}", .0)]
+    SeekError(io::Error),
+    #[error("Db file read error: {:?}", .0)]
+    ReadError(io::Error),
+    #[error("Failed to convert parsed varint to u64")]
+    VarIntConversionFail,
+    #[error("Invalid UTF-8: {:?}", .0)]
+    InvalidUTF8(#[from] std::string::FromUtf8Error),
+    #[error("Invalid UTF-16: {:?}", .0)]
+    InvalidUTF16(#[from] std::string::FromUtf16Error),
+    #[error("Found bad object type: {}", .0)]
+    FoundBadObjectType(String),
+    #[error("{}", .0)]
+    SerialTypeError(#[from] SerialTypeError),
+    #[error("Malformed record")]
+    MalformedRecord,
+    #[error("Found bad b-tree page type: {:#04x}", .0)]
+    BadPageType(u8),
+    #[error("Found unexpected b-tree page type: {:?}", .0)]
+    UnexpectedPageType(PageType),
+    #[error("Page {} is a pointer-map page, not a b-tree page", .0)]
+    UnexpectedPtrmapPage(u32),
+    #[error("Could not parse a CREATE TABLE statement of the schema: {}", .0)]
+    BadCreateStatement(SQLQueryParsingError),
+    #[error("Page {} has a corrupt free space layout", .0)]
+    BadFreeSpace(u32),
+    #[error("file is not a database")]
+    NotADatabase,
+    #[error("file is not a database: the header is unreadable, but the file size is a multiple of a page size: it may be encrypted")]
+    PossiblyEncrypted,
+    #[error("Invalid page size {}: expected a power of two between 512 and 65536", .0)]
+    InvalidPageSize(u16),
+    #[error("B-tree page {} is visited twice: the file is corrupt", .0)]
+    PageCycle(u32),
+    #[error("B-tree traversal visited more than {} pages", .0)]
+    TooManyPages(u64),
+    #[error("Page {} is past the end of the file: the file is truncated", .0)]
+    TruncatedFile(u32),
+    #[error("Found page number 0, which is not a page: the file is corrupt")]
+    NullPageNumber,
+    #[error("Cell payload of {} bytes is larger than the file: the file is corrupt", .0)]
+    PayloadTooLarge(u64),
+}
+
+#[derive(Debug, Error)]
+pub enum SerialTypeError {
+    #[error("Could not convert serial type: {:?}", .0)]
+    BadSerialNumber(u64),
+    #[error("Found serial type {}, which is reserved for internal use: the record is corrupt", .0)]
+    ReservedSerialNumber(u64),
+}
@@ -0,0 +1,1281 @@
+use std::cmp::Ordering;
+use std::io::prelude::*;
+
+use crate::btree::{read_table_leaf_cell, PageLayout};
+use crate::function::{ArithmeticOperator, ScalarFunction};
+use crate::output::{print_rows, NdjsonWriter};
+use crate::pattern::{glob, like};
+use crate::planner::{plan_count_non_null, plan_count_rows, plan_scan, ScanPlan, WhereColumn};
+use crate::query::{
+    parse_create_view, Comparison, CountNonNullQueryData, Having, Predicate, SQLQuery, SelectItem,
+    SelectQueryData, UnionAllQueryData,
+};
+use crate::record::{parse_record, parse_record_columns};
+use crate::schema::{
+    col_names_from_sql_create_stmt, declared_types_from_sql_create_stmt,
+    default_values_from_sql_create_stmt, fill_missing_values, find_table, find_view,
+    real_col_idxs_from_sql_create_stmt, rowid_alias_col_idx_from_sql_create_stmt,
+};
+use crate::value::{apply_real_affinity, compare_values, Affinity};
+use crate::{Database, OutputConfig, OutputMode, SQLQueryError, SQLiteInternalError, Value};
+
+/// A SELECT item resolved against the table columns. `*` resolves to one `Column` per column.
+enum OutputCol<'a> {
+    Column(usize), // index of the column in the record
+    Literal(&'a Value),
+    Count(Option<usize>), // counted column, None for COUNT(*)
+    Function(ScalarFunction, Box<OutputCol<'a>>),
+    Arithmetic(ArithmeticOperator, Box<OutputCol<'a>>, Box<OutputCol<'a>>),
+    Cast(Affinity, Box<OutputCol<'a>>),
+}
+
+impl OutputCol<'_> {
+    /// Flag the table columns the value of the column is computed from.
+    fn mark_decoded(&self, decoded_cols: &mut [bool]) {
+        match self {
+            OutputCol::Column(col_idx) | OutputCol::Count(Some(col_idx)) => {
+                decoded_cols[*col_idx] = true
+            }
+            OutputCol::Literal(_) | OutputCol::Count(None) => {}
+            OutputCol::Function(_, argument) | OutputCol::Cast(_, argument) => {
+                argument.mark_decoded(decoded_cols)
+            }
+            OutputCol::Arithmetic(_, left, right) => {
+                left.mark_decoded(decoded_cols);
+                right.mark_decoded(decoded_cols);
+            }
+        }
+    }
+
+    /// The value of the column for a group of rows, given the record of its last row.
+    fn eval(&self, group: &Group, record: &[Value]) -> Result<Value, SQLiteInternalError> {
+        match self {
+            OutputCol::Column(col_idx) => record
+                .get(*col_idx)
+                .cloned()
+                .ok_or(SQLiteInternalError::MalformedRecord),
+            OutputCol::Literal(value) => Ok((*value).clone()),
+            OutputCol::Count(col_idx) => Ok(count(group, *col_idx)),
+            OutputCol::Function(function, argument) => {
+                Ok(function.apply(argument.eval(group, record)?))
+            }
+            OutputCol::Cast(affinity, argument) => Ok(affinity.cast(argument.eval(group, record)?)),
+            OutputCol::Arithmetic(operator, left, right) => {
+                Ok(operator.apply(left.eval(group, record)?, right.eval(group, record)?))
+            }
+        }
+    }
+}
+
+/// Resolve a SELECT item against the table columns.
+fn resolve_item<'a>(
+    item: &'a SelectItem,
+    cols: &[String],
+    table_name: &str,
+) -> Result<Vec<OutputCol<'a>>, SQLQueryError> {
+    let output_col = match item {
+        SelectItem::AllColumns => return Ok((0..cols.len()).map(OutputCol::Column).collect()),
+        SelectItem::Column(column_name) => {
+            OutputCol::Column(column_index(cols, column_name, table_name)?)
+        }
+        SelectItem::Literal(value) => OutputCol::Literal(value),
+        SelectItem::Count(column_name) => OutputCol::Count(
+            column_name
+                .as_ref()
+                .map(|column_name| column_index(cols, column_name, table_name))
+                .transpose()?,
+        ),
+        SelectItem::Function {
+            function, argument, ..
+        } => {
+            let argument = resolve_value_item(argument, cols, table_name)?;
+            OutputCol::Function(*function, Box::new(argument))
+        }
+        SelectItem::Arithmetic {
+            operator,
+            left,
+            right,
+        } => OutputCol::Arithmetic(
+            *operator,
+            Box::new(resolve_value_item(left, cols, table_name)?),
+            Box::new(resolve_value_item(right, cols, table_name)?),
+        ),
+        SelectItem::Cast {
+            argument,
+            type_name,
+        } => OutputCol::Cast(
+            Affinity::from_type_name(type_name),
+            Box::new(resolve_value_item(argument, cols, table_name)?),
+        ),
+    };
+    Ok(vec![output_col])
+}
+
+/// Resolve a SELECT item standing for a single value, e.g. a function argument.
+fn resolve_value_item<'a>(
+    item: &'a SelectItem,
+    cols: &[String],
+    table_name: &str,
+) -> Result<OutputCol<'a>, SQLQueryError> {
+    // The parser only takes single value items as operands
+    let [output_col] = <[OutputCol; 1]>::try_from(resolve_item(item, cols, table_name)?)
+        .unwrap_or_else(|_| panic!("operand should be a single value"));
+    Ok(output_col)
+}
+
+/// The declared types of the output columns of a SELECT item, one per column. Only values read
+/// straight from a table column have one.
+fn item_declared_types(
+    item: &SelectItem,
+    cols: &[String],
+    col_types: &[Option<String>],
+) -> Vec<Option<String>> {
+    let col_type = |col_idx: usize| col_types.get(col_idx).cloned().flatten();
+    match item {
+        SelectItem::AllColumns => (0..cols.len()).map(col_type).collect(),
+        SelectItem::Column(column_name) => {
+            vec![cols
+                .iter()
+                .position(|col| col == column_name)
+                .and_then(col_type)]
+        }
+        _ => vec![None],
+    }
+}
+
+/// Names of the output columns of a SELECT item, like sqlite3 names them.
+fn item_names(item: &SelectItem, cols: &[String]) -> Vec<String> {
+    match item {
+        SelectItem::AllColumns => cols.to_vec(),
+        SelectItem::Column(column_name) => vec![column_name.clone()],
+        SelectItem::Literal(Value::Null) => vec!["NULL".to_string()],
+        SelectItem::Literal(Value::Text(text)) => {
+            vec![format!("'{}'", text.replace('\'', "''"))]
+        }
+        SelectItem::Literal(value) => vec![value.to_string()],
+        SelectItem::Count(None) => vec!["COUNT(*)".to_string()],
+        SelectItem::Count(Some(column_name)) => vec![format!("COUNT({column_name})")],
+        SelectItem::Function { name, argument, .. } => {
+            vec![format!("{name}({})", item_names(argument, cols).concat())]
+        }
+        SelectItem::Cast {
+            argument,
+            type_name,
+        } => vec![format!(
+            "CAST({} AS {type_name})",
+            item_names(argument, cols).concat()
+        )],
+        SelectItem::Arithmetic {
+            operator,
+            left,
+            right,
+        } => {
+            // Operands binding looser than the operator were parenthesized: so is their name.
+            // Operators of the same precedence are left-associative.
+            let operand_name = |operand: &SelectItem, is_right: bool| {
+                let name = item_names(operand, cols).concat();
+                match operand {
+                    SelectItem::Arithmetic {
+                        operator: operand_operator,
+                        ..
+                    } if operand_operator.precedence() > operator.precedence()
+                        || (is_right && operand_operator.precedence() == operator.precedence()) =>
+                    {
+                        format!("({name})")
+                    }
+                    _ => name,
+                }
+            };
+            vec![format!(
+                "{} {} {}",
+                operand_name(left, false),
+                operator.symbol(),
+                operand_name(right, true)
+            )]
+        }
+    }
+}
+
+/// A group of rows of an aggregate query, i.e. with GROUP BY, HAVING or COUNT items. A plain
+/// query has one group per row.
+type Group = Vec<(i64, Vec<Value>)>;
+
+/// The value of `COUNT(*)`, or `COUNT(<column>)` i.e. the number of non-NULL values, over a group.
+fn count(group: &[(i64, Vec<Value>)], col_idx: Option<usize>) -> Value {
+    let nb_rows = match col_idx {
+        None => group.len(),
+        Some(col_idx) => group
+            .iter()
+            .filter(|(_, record)| record.get(col_idx).is_some_and(|v| *v != Value::Null))
+            .count(),
+    };
+    Value::Integer(nb_rows as i64)
+}
+
+/// The rowid and record of the last row of a group, or the given NULL record if it is empty.
+fn last_row<'a>(group: &'a Group, null_record: &'a [Value]) -> (i64, &'a [Value]) {
+    group
+        .last()
+        .map_or((0, null_record), |(rowid, record)| (*rowid, record))
+}
+
+/// Compare two records on the given columns, most significant first. A record too short to hold
+/// a column compares equal on it.
+fn compare_records(a: &[Value], b: &[Value], col_idxs: &[usize]) -> Ordering {
+    col_idxs
+        .iter()
+        .map(|&col_idx| match (a.get(col_idx), b.get(col_idx)) {
+            (Some(a), Some(b)) => compare_values(a, b),
+            _ => Ordering::Equal,
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Compare two records on the given sort keys, most significant first: the index of a column, and
+/// whether it sorts in descending order.
+fn compare_sort_keys(a: &[Value], b: &[Value], sort_keys: &[(usize, bool)]) -> Ordering {
+    sort_keys
+        .iter()
+        .map(|&(col_idx, descending)| {
+            let ordering = compare_records(a, b, &[col_idx]);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Whether a name refers to the rowid of a table row: 'rowid', 'oid' or '_rowid_', in any case.
+fn is_rowid_name(name: &str) -> bool {
+    ["rowid", "oid", "_rowid_"]
+        .iter()
+        .any(|rowid_name| rowid_name.eq_ignore_ascii_case(name))
+}
+
+/// A WHERE condition resolved for evaluation, checking the value of a column.
+enum Filter {
+    OneOf(Vec<Value>), // accepted values
+    Like {
+        pattern: Value,
+        escape: Option<char>,
+    },
+    Glob(Value), // pattern
+    Compare(Comparison, Value),
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            // NULL is never equal to anything, not even NULL: `x IN (1, NULL)` only matches 1,
+            // and `x IN (NULL)` matches nothing
+            Filter::OneOf(accepted_values) => {
+                *value != Value::Null
+                    && accepted_values.iter().any(|accepted| {
+                        *accepted != Value::Null
+                            && compare_values(value, accepted) == Ordering::Equal
+                    })
+            }
+            // NULL is never LIKE anything. Other values are matched as text, e.g. 12 LIKE '1%'.
+            Filter::Like { pattern, escape } => match (pattern, value) {
+                (Value::Null, _) | (_, Value::Null) => false,
+                (pattern, value) => like(&as_text(pattern), &as_text(value), *escape),
+            },
+            Filter::Glob(pattern) => match (pattern, value) {
+                (Value::Null, _) | (_, Value::Null) => false,
+                (pattern, value) => glob(&as_text(pattern), &as_text(value)),
+            },
+            // NULL is never compared, e.g. NULL != 1 is not true
+            Filter::Compare(comparison, operand) => match (value, operand) {
+                (Value::Null, _) | (_, Value::Null) => false,
+                (value, operand) => comparison.holds(compare_values(value, operand)),
+            },
+        }
+    }
+}
+
+/// The text form of a non-NULL value, as used for LIKE and GLOB: blobs are read as UTF-8 text.
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        value => value.to_string(),
+    }
+}
+
+/// The output of a SELECT query: its columns, and its rows along with the rowid of the table row
+/// each one comes from.
+#[derive(Default)]
+struct SelectOutput {
+    columns: Vec<ColumnMeta>,
+    rows: Vec<(i64, Vec<Value>)>,
+}
+
+/// Where the output of a SELECT query goes: its columns first, then each of its rows along with
+/// the rowid of the table row it comes from, as soon as the query produces it.
+pub(crate) trait RowSink {
+    fn set_columns(&mut self, columns: Vec<ColumnMeta>);
+    fn push_row(&mut self, rowid: i64, row: Vec<Value>);
+}
+
+impl RowSink for SelectOutput {
+    fn set_columns(&mut self, columns: Vec<ColumnMeta>) {
+        self.columns = columns;
+    }
+
+    fn push_row(&mut self, rowid: i64, row: Vec<Value>) {
+        self.rows.push((rowid, row));
+    }
+}
+
+/// An output column of a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMeta {
+    pub name: String, // as printed in the header, e.g. 'name' or 'COUNT(*)'
+    /// Declared type of the table column the values are read from, as written in its 'CREATE'
+    /// statement. None for computed values, and for columns declared without a type.
+    pub declared_type: Option<String>,
+}
+
+/// The output of a query: its columns, and the values of its rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<ColumnMeta>,
+    pub rows: Vec<Vec<Value>>,
+    pub(crate) rowids: Vec<Option<i64>>, // rowid of the table row each row comes from, if any
+}
+
+impl ResultSet {
+    /// A result set of a single computed value, e.g. a count.
+    fn single_value(col_name: String, value: Value) -> ResultSet {
+        ResultSet {
+            columns: vec![ColumnMeta {
+                name: col_name,
+                declared_type: None,
+            }],
+            rows: vec![vec![value]],
+            rowids: vec![None],
+        }
+    }
+
+    /// The names of the output columns, in order.
+    pub fn col_names(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .map(|column| column.name.as_str())
+            .collect()
+    }
+
+    /// Keep the first `nb_rows` rows only.
+    pub(crate) fn truncate(&mut self, nb_rows: usize) {
+        self.rows.truncate(nb_rows);
+        self.rowids.truncate(nb_rows);
+    }
+}
+
+impl From<SelectOutput> for ResultSet {
+    fn from(select_output: SelectOutput) -> Self {
+        let (rowids, rows) = select_output
+            .rows
+            .into_iter()
+            .map(|(rowid, row)| (Some(rowid), row))
+            .unzip();
+        ResultSet {
+            columns: select_output.columns,
+            rows,
+            rowids,
+        }
+    }
+}
+
+/// Run a query, and print its output.
+pub(crate) fn handle_sql_query(
+    sql_query: &SQLQuery,
+    database: &mut Database<impl Read + Seek>,
+    output_config: &OutputConfig,
+) -> Result<(), SQLQueryError> {
+    match (sql_query, output_config.mode) {
+        // ndjson lines are written as the rows come, rather than once the query is done
+        (SQLQuery::Select(select_query), OutputMode::Ndjson) => {
+            let mut ndjson_writer =
+                NdjsonWriter::new(std::io::stdout().lock(), output_config.max_rows);
+            select_rows_into(select_query, database, &mut ndjson_writer)?;
+            ndjson_writer.finish();
+        }
+        _ => print_rows(run_sql_query(sql_query, database)?, output_config),
+    }
+    Ok(())
+}
+
+/// Run a query, and collect its output.
+pub(crate) fn run_sql_query(
+    sql_query: &SQLQuery,
+    database: &mut Database<impl Read + Seek>,
+) -> Result<ResultSet, SQLQueryError> {
+    let result_set = match sql_query {
+        SQLQuery::CountRows(target_tbl_name) => {
+            let table_rows = database.schema_table_rows()?;
+
+            let nb_rows = match find_table(&table_rows, target_tbl_name) {
+                // Every cell in the leaves of the table b-tree is a row, like every index entry
+                Some(target_table_row) => {
+                    let scan_plan = plan_count_rows(&table_rows, target_tbl_name);
+                    explain_plan(database, &scan_plan, target_tbl_name);
+                    match scan_plan {
+                        ScanPlan::IndexScan(index) => {
+                            database.index_entry_count(index.root_page)?
+                        }
+                        _ => database.table_leaf_cells(target_table_row.root_page)?.len(),
+                    }
+                }
+                // A view has to be run to count its rows
+                None => select_rows(
+                    &select_all(target_tbl_name, SelectItem::AllColumns),
+                    database,
+                )?
+                .rows
+                .len(),
+            };
+
+            let col_name = "COUNT(*)".to_string();
+            ResultSet::single_value(col_name, Value::Integer(nb_rows as i64))
+        }
+        SQLQuery::CountNonNull(CountNonNullQueryData {
+            table_name,
+            column_name,
+        }) => {
+            let schema_rows = database.schema_table_rows()?;
+
+            let Some(target_table_row) = find_table(&schema_rows, table_name) else {
+                // A view has to be run to count its non-NULL values
+                let select_query = select_all(table_name, SelectItem::Column(column_name.clone()));
+                let nb_non_null = select_rows(&select_query, database)?
+                    .rows
+                    .into_iter()
+                    .filter(|(_rowid, row)| row.first().is_some_and(|v| *v != Value::Null))
+                    .count();
+                let col_name = format!("COUNT({column_name})");
+                return Ok(ResultSet::single_value(
+                    col_name,
+                    Value::Integer(nb_non_null as i64),
+                ));
+            };
+
+            let scan_plan = plan_count_non_null(&schema_rows, table_name, column_name);
+            explain_plan(database, &scan_plan, table_name);
+            let nb_non_null = match scan_plan {
+                ScanPlan::IndexScan(index) => database
+                    .index_records(index.root_page)?
+                    .into_iter()
+                    .filter(|record| record.first().is_some_and(|v| *v != Value::Null))
+                    .count(),
+                _ => {
+                    let cols = col_names_from_sql_create_stmt(&target_table_row.sql)?;
+                    let col_idx = column_index(&cols, column_name, table_name)?;
+                    let is_rowid_alias =
+                        rowid_alias_col_idx_from_sql_create_stmt(&target_table_row.sql)
+                            == Some(col_idx);
+                    let default_values = default_values_from_sql_create_stmt(&target_table_row.sql);
+                    let encoding = database.text_encoding()?;
+                    let page_layout = PageLayout::read(&mut database.reader)?;
+
+                    let mut nb_non_null = 0;
+                    for (page_offset, cell_offset) in
+                        database.table_leaf_cells(target_table_row.root_page)?
+                    {
+                        let (_rowid, payload) = read_table_leaf_cell(
+                            page_offset,
+                            cell_offset,
+                            page_layout,
+                            &mut database.reader,
+                        )?;
+                        // The rowid alias column is stored as NULL, but its value is the rowid
+                        let mut record = parse_record(&payload, encoding)?;
+                        fill_missing_values(&mut record, &default_values);
+                        if is_rowid_alias || record.get(col_idx).is_some_and(|v| *v != Value::Null)
+                        {
+                            nb_non_null += 1;
+                        }
+                    }
+                    nb_non_null
+                }
+            };
+
+            let col_name = format!("COUNT({column_name})");
+            ResultSet::single_value(col_name, Value::Integer(nb_non_null as i64))
+        }
+        SQLQuery::Select(select_query) => select_rows(select_query, database)?.into(),
+        SQLQuery::UnionAll(UnionAllQueryData {
+            selects,
+            order_by,
+            limit,
+        }) => {
+            // Like in SQLite, the output columns are named after those of the first SELECT
+            let mut columns = Vec::new();
+            let mut rows = Vec::new();
+            for (select_idx, select_query) in selects.iter().enumerate() {
+                let select_output = select_rows(select_query, database)?;
+                if select_idx == 0 {
+                    columns = select_output.columns;
+                } else if select_output.columns.len() != columns.len() {
+                    return Err(SQLQueryError::InvalidSQL(
+                        "SELECTs to the left and right of UNION ALL do not have the same number of result columns".to_string(),
+                    ));
+                }
+                rows.extend(select_output.rows);
+            }
+
+            let sort_keys = order_by
+                .iter()
+                .enumerate()
+                .map(|(term_idx, term)| {
+                    columns
+                        .iter()
+                        .position(|column| column.name == term.column_name)
+                        .map(|col_idx| (col_idx, term.descending))
+                        .ok_or_else(|| {
+                            SQLQueryError::InvalidSQL(format!(
+                                "ORDER BY term {} does not match any column in the result set",
+                                term_idx + 1
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if !sort_keys.is_empty() {
+                rows.sort_by(|(_, a), (_, b)| compare_sort_keys(a, b, &sort_keys));
+            }
+            if let Some(limit) = *limit {
+                rows.truncate(limit);
+            }
+
+            SelectOutput { columns, rows }.into()
+        }
+    };
+    Ok(result_set)
+}
+
+/// Print how the rows of a table are read, when the database explains its queries. Like traces, the
+/// plan goes to stderr, so that the query results alone go to stdout.
+fn explain_plan(database: &Database<impl Read + Seek>, scan_plan: &ScanPlan, table_name: &str) {
+    if database.explain {
+        eprintln!("plan: {}", scan_plan.describe(table_name));
+    }
+}
+
+/// Position of a column among the columns of a table.
+fn column_index(
+    cols: &[String],
+    column_name: &str,
+    table_name: &str,
+) -> Result<usize, SQLQueryError> {
+    cols.iter()
+        .position(|s| s == column_name)
+        .ok_or_else(|| SQLQueryError::NoSuchColumn {
+            column_name: column_name.to_string(),
+            table_name: table_name.to_string(),
+        })
+}
+
+/// A `SELECT <item> FROM <table>` query, without WHERE nor ORDER BY clause.
+fn select_all(table_name: &str, select_item: SelectItem) -> SelectQueryData {
+    SelectQueryData {
+        table_name: table_name.to_string(),
+        select_items: vec![select_item],
+        where_clause: None,
+        group_by: Vec::new(),
+        having: None,
+        order_by: Vec::new(),
+        limit: None,
+    }
+}
+
+/// Where the rows of a SELECT query come from.
+enum RowSource {
+    Table {
+        root_page: u32,
+        rowid_alias_col_idx: Option<usize>,
+        real_col_idxs: Vec<usize>, // columns with REAL affinity
+        default_values: Vec<Value>,
+    },
+    View(Vec<(i64, Vec<Value>)>), // the rows of the view's query, already run
+}
+
+/// Run a SELECT query, and collect its output.
+fn select_rows(
+    select_query: &SelectQueryData,
+    database: &mut Database<impl Read + Seek>,
+) -> Result<SelectOutput, SQLQueryError> {
+    let mut select_output = SelectOutput::default();
+    select_rows_into(select_query, database, &mut select_output)?;
+    Ok(select_output)
+}
+
+/// Run a SELECT query, and push its output to a sink. Without ORDER BY nor aggregate, each row is
+/// pushed as soon as it is read from the table, e.g. for ndjson output to stream. Otherwise, the
+/// rows are pushed once they are all read, grouped and sorted.
+pub(crate) fn select_rows_into(
+    select_query: &SelectQueryData,
+    database: &mut Database<impl Read + Seek>,
+    sink: &mut impl RowSink,
+) -> Result<(), SQLQueryError> {
+    let SelectQueryData {
+        table_name,
+        select_items,
+        where_clause,
+        group_by,
+        having,
+        order_by,
+        limit,
+    } = select_query;
+    let table_rows = database.schema_table_rows()?;
+
+    // A view is queried by running its own query first, its output becoming the rows to select
+    // from
+    let (cols, col_types, row_source) = match find_table(&table_rows, table_name) {
+        Some(target_table_row) => {
+            // parsing the sql stmt to extract columns names
+            let cols = col_names_from_sql_create_stmt(&target_table_row.sql)?;
+            let col_types = declared_types_from_sql_create_stmt(&target_table_row.sql);
+            let row_source = RowSource::Table {
+                root_page: target_table_row.root_page,
+                rowid_alias_col_idx: rowid_alias_col_idx_from_sql_create_stmt(
+                    &target_table_row.sql,
+                ),
+                real_col_idxs: real_col_idxs_from_sql_create_stmt(&target_table_row.sql),
+                default_values: default_values_from_sql_create_stmt(&target_table_row.sql),
+            };
+            (cols, col_types, row_source)
+        }
+        None => {
+            let view_row = find_view(&table_rows, table_name)
+                .ok_or_else(|| SQLQueryError::InvalidSQL(format!("no such table: {table_name}")))?;
+            let view = parse_create_view(&view_row.sql).map_err(|err| {
+                SQLQueryError::NotImplementedYet(format!(
+                    "view '{table_name}' is not a supported single-table SELECT: {err}"
+                ))
+            })?;
+            let view_output = select_rows(&view.select_query, database)?;
+            // The columns of a view keep the declared types of the columns they are read from
+            let (view_col_names, col_types) = view_output
+                .columns
+                .into_iter()
+                .map(|column| (column.name, column.declared_type))
+                .unzip();
+            let cols = view.col_names.unwrap_or(view_col_names);
+            (cols, col_types, RowSource::View(view_output.rows))
+        }
+    };
+    // Output columns follow the order of the SELECT items, whatever the order of the table
+    // columns they are read from, e.g. `SELECT color, name` outputs the color first
+    let mut output_cols = Vec::new();
+    for item in select_items {
+        output_cols.extend(resolve_item(item, &cols, table_name)?);
+    }
+    let output_columns = select_items
+        .iter()
+        .flat_map(|item| {
+            item_names(item, &cols)
+                .into_iter()
+                .zip(item_declared_types(item, &cols, &col_types))
+        })
+        .map(|(name, declared_type)| ColumnMeta {
+            name,
+            declared_type,
+        })
+        .collect::<Vec<_>>();
+    sink.set_columns(output_columns);
+
+    // Resolve each WHERE condition into the column to check and the filter its value must pass
+    let conditions = where_clause
+        .iter()
+        .flat_map(|where_clause| &where_clause.conditions)
+        .collect::<Vec<_>>();
+    let is_table = matches!(row_source, RowSource::Table { .. });
+    // A table row can also be filtered on its rowid, unless a column has the same name
+    let where_column = |column_name: &str| match column_index(&cols, column_name, table_name) {
+        Ok(col_idx) => Ok(WhereColumn::Column(col_idx)),
+        Err(_) if is_table && is_rowid_name(column_name) => Ok(WhereColumn::Rowid),
+        Err(err) => Err(err),
+    };
+    // Conditions comparing two columns of the row are checked apart: they have no fixed operand
+    // to filter on, nor to seek
+    let mut filter_conditions = Vec::with_capacity(conditions.len());
+    let mut where_filters = Vec::with_capacity(conditions.len());
+    let mut column_comparisons = Vec::new();
+    for &condition in &conditions {
+        let where_col = where_column(&condition.column_name)?;
+        if let Predicate::CompareColumn(comparison, other_column_name) = &condition.predicate {
+            column_comparisons.push((where_col, *comparison, where_column(other_column_name)?));
+            continue;
+        }
+        let filter = filter_from_predicate(&condition.predicate, database)?;
+        filter_conditions.push(condition);
+        where_filters.push((where_col, filter));
+    }
+
+    let group_by_col_idxs = group_by
+        .iter()
+        .map(|column_name| column_index(&cols, column_name, table_name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let having_filter = match having {
+        Some(Having {
+            count_column,
+            predicate,
+        }) => {
+            let count_col_idx = count_column
+                .as_ref()
+                .map(|column_name| column_index(&cols, column_name, table_name))
+                .transpose()?;
+            Some((count_col_idx, filter_from_predicate(predicate, database)?))
+        }
+        None => None,
+    };
+    let sort_keys = order_by
+        .iter()
+        .map(|term| {
+            column_index(&cols, &term.column_name, table_name)
+                .map(|col_idx| (col_idx, term.descending))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Only the columns the query looks at are decoded from table records: selected, but also
+    // filtered, grouped and sorted ones, e.g. 'color' in `SELECT name FROM apples WHERE color =
+    // 'Red'`. Each one is decoded once per row, however many clauses use it: filters and output
+    // columns all read the values of the same decoded record.
+    let mut decoded_cols = vec![false; cols.len()];
+    for output_col in &output_cols {
+        output_col.mark_decoded(&mut decoded_cols);
+    }
+    let filtered_col_idxs = where_filters
+        .iter()
+        .map(|(where_col, _)| where_col)
+        .chain(
+            column_comparisons
+                .iter()
+                .flat_map(|(left_col, _, right_col)| [left_col, right_col]),
+        )
+        .filter_map(|where_col| match where_col {
+            WhereColumn::Column(col_idx) => Some(*col_idx),
+            WhereColumn::Rowid => None,
+        });
+    let having_col_idxs = having_filter
+        .iter()
+        .filter_map(|(count_col_idx, _)| *count_col_idx);
+    for col_idx in filtered_col_idxs
+        .chain(group_by_col_idxs.iter().copied())
+        .chain(having_col_idxs)
+        .chain(sort_keys.iter().map(|&(col_idx, _)| col_idx))
+    {
+        decoded_cols[col_idx] = true;
+    }
+
+    // An aggregate query outputs one row per group of rows, in group order. Without GROUP BY,
+    // all the rows make up a single group, even when there are none.
+    let is_aggregate = !group_by.is_empty()
+        || having.is_some()
+        || select_items.iter().any(SelectItem::is_aggregate);
+    let is_streamed = !is_aggregate && sort_keys.is_empty();
+    let mut nb_streamed_rows = 0;
+
+    let mut matching_rows = Vec::new();
+    let mut keep_if_match = |rowid: i64, record: Vec<Value>| {
+        let rowid_value = Value::Integer(rowid);
+        let where_value = |where_col: &WhereColumn| match where_col {
+            WhereColumn::Column(col_idx) => record
+                .get(*col_idx)
+                .ok_or(SQLiteInternalError::MalformedRecord),
+            WhereColumn::Rowid => Ok(&rowid_value),
+        };
+        for (where_col, filter) in &where_filters {
+            if !filter.matches(where_value(where_col)?) {
+                return Ok(());
+            }
+        }
+        for (left_col, comparison, right_col) in &column_comparisons {
+            // NULL is never compared, e.g. NULL = NULL is not true
+            let is_match = match (where_value(left_col)?, where_value(right_col)?) {
+                (Value::Null, _) | (_, Value::Null) => false,
+                (left, right) => comparison.holds(compare_values(left, right)),
+            };
+            if !is_match {
+                return Ok(());
+            }
+        }
+        if !is_streamed {
+            matching_rows.push((rowid, record));
+            return Ok(());
+        }
+        if limit.is_some_and(|limit| nb_streamed_rows >= limit) {
+            return Ok(());
+        }
+        let group = vec![(rowid, record)];
+        let output_row = output_cols
+            .iter()
+            .map(|output_col| output_col.eval(&group, &group[0].1))
+            .collect::<Result<Vec<_>, _>>()?;
+        sink.push_row(rowid, output_row);
+        nb_streamed_rows += 1;
+        Ok::<_, SQLiteInternalError>(())
+    };
+
+    match row_source {
+        RowSource::Table {
+            root_page,
+            rowid_alias_col_idx,
+            real_col_idxs,
+            default_values,
+        } => {
+            let where_cols = where_filters
+                .iter()
+                .map(|(where_col, _)| where_col)
+                .collect::<Vec<_>>();
+            let scan_plan = plan_scan(
+                &table_rows,
+                table_name,
+                &filter_conditions,
+                &where_cols,
+                rowid_alias_col_idx,
+            );
+            explain_plan(database, &scan_plan, table_name);
+            let leaf_cells = match scan_plan {
+                ScanPlan::RowidRange(rowid_range) => {
+                    database.table_leaf_cells_in_range(root_page, &rowid_range)?
+                }
+                ScanPlan::IndexSeek { index, key, .. } => {
+                    let mut leaf_cells = Vec::new();
+                    for index_record in database.index_seek(index.root_page, &key)? {
+                        // The last column of an index record is the rowid of the table row
+                        let Some(&Value::Integer(rowid)) = index_record.last() else {
+                            return Err(SQLiteInternalError::MalformedRecord.into());
+                        };
+                        let leaf_cell = database
+                            .table_leaf_cell_by_rowid(root_page, rowid)?
+                            .ok_or(SQLiteInternalError::MalformedRecord)?;
+                        leaf_cells.push(leaf_cell);
+                    }
+                    leaf_cells
+                }
+                ScanPlan::FullScan | ScanPlan::IndexScan(_) => {
+                    database.table_leaf_cells(root_page)?
+                }
+            };
+
+            let encoding = database.text_encoding()?;
+            let page_layout = PageLayout::read(&mut database.reader)?;
+            for (page_offset, cell_offset) in leaf_cells {
+                let (rowid, payload) = read_table_leaf_cell(
+                    page_offset,
+                    cell_offset,
+                    page_layout,
+                    &mut database.reader,
+                )?;
+                let mut record = parse_record_columns(&payload, &decoded_cols, encoding)?;
+                fill_missing_values(&mut record, &default_values);
+
+                // An 'INTEGER PRIMARY KEY' column is an alias for the rowid: its value is stored
+                // as NULL in the record, the actual value being the rowid.
+                if let Some(alias_value) = rowid_alias_col_idx.and_then(|idx| record.get_mut(idx)) {
+                    *alias_value = Value::Integer(rowid);
+                }
+                for &col_idx in &real_col_idxs {
+                    if let Some(value) = record.get_mut(col_idx) {
+                        apply_real_affinity(value);
+                    }
+                }
+                keep_if_match(rowid, record)?;
+            }
+        }
+        RowSource::View(rows) => {
+            for (rowid, row) in rows {
+                keep_if_match(rowid, row)?;
+            }
+        }
+    }
+
+    if is_streamed {
+        return Ok(());
+    }
+
+    let mut groups: Vec<Group> = if !is_aggregate {
+        matching_rows.into_iter().map(|row| vec![row]).collect()
+    } else if group_by_col_idxs.is_empty() {
+        vec![matching_rows]
+    } else {
+        matching_rows.sort_by(|(_, a), (_, b)| compare_records(a, b, &group_by_col_idxs));
+        let mut groups: Vec<Group> = Vec::new();
+        for (rowid, record) in matching_rows {
+            match groups.last_mut() {
+                Some(group)
+                    if compare_records(&group[0].1, &record, &group_by_col_idxs).is_eq() =>
+                {
+                    group.push((rowid, record))
+                }
+                _ => groups.push(vec![(rowid, record)]),
+            }
+        }
+        groups
+    };
+    if let Some((count_col_idx, filter)) = &having_filter {
+        groups.retain(|group| filter.matches(&count(group, *count_col_idx)));
+    }
+
+    // Other columns of a group take their values from its last row, like in SQLite. Those of the
+    // empty group are NULL.
+    let null_record = vec![Value::Null; cols.len()];
+
+    if !sort_keys.is_empty() {
+        groups.sort_by(|a, b| {
+            let (_, a) = last_row(a, &null_record);
+            let (_, b) = last_row(b, &null_record);
+            compare_sort_keys(a, b, &sort_keys)
+        });
+    }
+    if let Some(limit) = *limit {
+        groups.truncate(limit);
+    }
+
+    for group in &groups {
+        let (rowid, record) = last_row(group, &null_record);
+        let output_row = output_cols
+            .iter()
+            .map(|output_col| output_col.eval(group, record))
+            .collect::<Result<Vec<_>, _>>()?;
+        sink.push_row(rowid, output_row);
+    }
+    Ok(())
+}
+
+/// Resolve the predicate of a condition into a filter. A sub-query is run once, up front, to build
+/// the set of accepted values.
+fn filter_from_predicate(
+    predicate: &Predicate,
+    database: &mut Database<impl Read + Seek>,
+) -> Result<Filter, SQLQueryError> {
+    Ok(match predicate {
+        Predicate::Eq(value) => Filter::OneOf(vec![value.clone()]),
+        Predicate::In(values) => Filter::OneOf(values.clone()),
+        Predicate::InSubquery(subquery) => Filter::OneOf(
+            select_rows(subquery, database)?
+                .rows
+                .into_iter()
+                .map(|(_rowid, row)| match <[Value; 1]>::try_from(row) {
+                    Ok([value]) => Ok(value),
+                    Err(_) => Err(SQLQueryError::InvalidSQL(
+                        "sub-query in IN (...) must select a single column".to_string(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Predicate::Like { pattern, escape } => Filter::Like {
+            pattern: pattern.clone(),
+            escape: *escape,
+        },
+        Predicate::Glob(pattern) => Filter::Glob(pattern.clone()),
+        Predicate::Compare(comparison, value) => Filter::Compare(*comparison, value.clone()),
+        Predicate::CompareColumn(..) => {
+            return Err(SQLQueryError::NotImplementedYet(
+                "comparing COUNT(...) to a column in HAVING".to_string(),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    use crate::record::DECODED_VALUES;
+    use crate::testing::{open_fixture, query_rows, text};
+    use crate::{SQLQueryParsingError, SQLiteError};
+
+    #[test]
+    fn unknown_column_names_the_column_and_its_table() {
+        let mut database = open_fixture("sample.db");
+        for sql in [
+            "SELECT bogus FROM apples",
+            "SELECT name FROM apples WHERE bogus = 1",
+        ] {
+            let sql_query = sql.parse::<SQLQuery>().unwrap();
+            match database.execute(&sql_query, &OutputConfig::default()) {
+                Err(SQLQueryError::NoSuchColumn {
+                    column_name,
+                    table_name,
+                }) => assert_eq!(
+                    (column_name.as_str(), table_name.as_str()),
+                    ("bogus", "apples")
+                ),
+                result => panic!("'{sql}' should fail with no such column, got {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn bound_parameters_filter_rows() {
+        let mut database = open_fixture("sample.db");
+        let sql = "SELECT id FROM apples WHERE name = ?";
+        // The quote needs no escaping
+        for (name, ids) in [("Fuji", vec![2]), ("Granny's", vec![])] {
+            let rows = database.query(sql, &[text(name)]).unwrap().rows;
+            assert_eq!(
+                rows,
+                ids.into_iter()
+                    .map(|id| vec![Value::Integer(id)])
+                    .collect::<Vec<_>>()
+            );
+        }
+        let rows = database
+            .query(
+                "SELECT name FROM apples WHERE id > ? AND color = ?",
+                &[Value::Integer(1), text("Yellow")],
+            )
+            .unwrap()
+            .rows;
+        assert_eq!(rows, vec![vec![text("Golden Delicious")]]);
+
+        assert!(matches!(
+            database.query(sql, &[]),
+            Err(SQLiteError::QueryParsingError(
+                SQLQueryParsingError::WrongParameterCount {
+                    nb_placeholders: 1,
+                    nb_parameters: 0
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn order_by_a_column_of_mixed_storage_classes() {
+        let mut database = open_fixture("types.db");
+        // NULL, then -7, 2.5 and 42, then '' and 'text', then x'00ff'
+        let ids = query_rows(&mut database, "SELECT id FROM mixed ORDER BY value");
+        assert_eq!(
+            ids,
+            [1, 6, 3, 2, 7, 4, 5]
+                .map(|id| vec![Value::Integer(id)])
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn columns_come_out_in_query_order() {
+        // Stored as id, name, color
+        let mut database = open_fixture("sample.db");
+        let result_set = database
+            .query("SELECT color, name, id FROM apples WHERE id = 2", &[])
+            .unwrap();
+        assert_eq!(result_set.col_names(), ["color", "name", "id"]);
+        assert_eq!(
+            result_set.rows,
+            vec![vec![text("Red"), text("Fuji"), Value::Integer(2)]]
+        );
+    }
+
+    #[test]
+    fn count_rows_from_an_index_matches_the_table() {
+        let mut database = open_fixture("misc.db");
+        let schema_rows = database.schema_table_rows().unwrap();
+        let ScanPlan::IndexScan(index) = plan_count_rows(&schema_rows, "fruits") else {
+            panic!("the rows of 'fruits' should be counted from one of its indexes");
+        };
+
+        // NULL keys have index entries too
+        let table_root_page = find_table(&schema_rows, "fruits").unwrap().root_page;
+        let nb_table_rows = database.table_leaf_cells(table_root_page).unwrap().len();
+        assert_eq!(nb_table_rows, 7);
+        assert_eq!(
+            database.index_entry_count(index.root_page).unwrap(),
+            nb_table_rows
+        );
+        assert_eq!(
+            query_rows(&mut database, "SELECT COUNT(*) FROM fruits"),
+            vec![vec![Value::Integer(7)]]
+        );
+    }
+
+    #[test]
+    fn negative_limit_means_no_limit() {
+        let mut database = open_fixture("sample.db");
+        for limit in ["-1", "-100"] {
+            let sql = format!("SELECT name FROM apples LIMIT {limit}");
+            assert_eq!(query_rows(&mut database, &sql).len(), 4);
+        }
+        assert_eq!(
+            query_rows(&mut database, "SELECT name FROM apples LIMIT 0"),
+            Vec::<Vec<Value>>::new()
+        );
+    }
+
+    #[test]
+    fn where_compares_two_columns_of_a_row() {
+        let mut database = open_fixture("ranges.db");
+        for (sql, labels) in [
+            (
+                "SELECT label FROM ranges WHERE low <= high",
+                vec!["ordered", "point"],
+            ),
+            (
+                "SELECT label FROM ranges WHERE low > high",
+                vec!["reversed"],
+            ),
+            ("SELECT label FROM ranges WHERE low = high", vec!["point"]),
+            (
+                "SELECT label FROM ranges WHERE high < low AND id > 1",
+                vec!["reversed"],
+            ),
+        ] {
+            let rows = query_rows(&mut database, sql);
+            let expected = labels
+                .into_iter()
+                .map(|label| vec![text(label)])
+                .collect::<Vec<_>>();
+            assert_eq!(rows, expected, "{sql}");
+        }
+
+        assert!(matches!(
+            database.query("SELECT label FROM ranges WHERE low < bogus", &[]),
+            Err(SQLiteError::SQLiteQueryError(SQLQueryError::NoSuchColumn { column_name, .. }))
+                if column_name == "bogus"
+        ));
+    }
+
+    #[test]
+    fn column_in_select_and_where_is_decoded_once_per_row() {
+        // Number of values decoded by a query of 'apples', whose 4 rows hold 'name' and 'color'
+        fn decoded_values(sql: &str) -> usize {
+            let mut database = open_fixture("sample.db");
+            let before = DECODED_VALUES.with(Cell::get);
+            assert_eq!(query_rows(&mut database, sql).len(), 4);
+            DECODED_VALUES.with(Cell::get) - before
+        }
+
+        let selected = decoded_values("SELECT name FROM apples");
+        assert_eq!(
+            decoded_values("SELECT name FROM apples WHERE name >= ''"),
+            selected
+        );
+        assert_eq!(
+            decoded_values("SELECT name, name FROM apples WHERE name >= '' ORDER BY name"),
+            selected
+        );
+        // Filtering on another column decodes it too
+        assert_eq!(
+            decoded_values("SELECT name FROM apples WHERE color >= ''"),
+            selected + 4
+        );
+    }
+
+    #[test]
+    fn result_set_holds_column_metadata_and_rows() {
+        let column = |name: &str, declared_type: Option<&str>| ColumnMeta {
+            name: name.to_string(),
+            declared_type: declared_type.map(str::to_string),
+        };
+
+        let mut database = open_fixture("misc.db");
+        let result_set = database
+            .query("SELECT color, id, name FROM fruits WHERE id = 3", &[])
+            .unwrap();
+        assert_eq!(
+            result_set.columns,
+            vec![
+                column("color", Some("TEXT")),
+                column("id", Some("INTEGER")),
+                column("name", Some("TEXT")),
+            ]
+        );
+        assert_eq!(
+            result_set.rows,
+            vec![vec![text("red"), Value::Integer(3), text("cherry")]]
+        );
+
+        let result_set = database.query("SELECT COUNT(*) FROM fruits", &[]).unwrap();
+        assert_eq!(result_set.columns, vec![column("COUNT(*)", None)]);
+        assert_eq!(result_set.rows, vec![vec![Value::Integer(7)]]);
+
+        // A column declared without a type
+        let mut database = open_fixture("types.db");
+        let result_set = database
+            .query("SELECT value FROM mixed WHERE id = 2", &[])
+            .unwrap();
+        assert_eq!(result_set.columns, vec![column("value", None)]);
+        assert_eq!(result_set.rows, vec![vec![Value::Integer(42)]]);
+    }
+
+    #[test]
+    fn ndjson_lines_are_written_as_the_scan_reads_rows() {
+        /// Records how many values were decoded so far each time a line is flushed.
+        #[derive(Default)]
+        struct ProgressWriter {
+            bytes: Vec<u8>,
+            decoded_values_at_flush: Vec<usize>,
+        }
+        impl Write for ProgressWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.bytes.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.decoded_values_at_flush
+                    .push(DECODED_VALUES.with(Cell::get));
+                Ok(())
+            }
+        }
+        fn write_ndjson(sql: &str) -> ProgressWriter {
+            let SQLQuery::Select(select_query) = sql.parse::<SQLQuery>().unwrap() else {
+                panic!("'{sql}' should be a SELECT query");
+            };
+            let mut progress_writer = ProgressWriter::default();
+            let mut ndjson_writer = NdjsonWriter::new(&mut progress_writer, None);
+            let mut database = open_fixture("pages.db");
+            select_rows_into(&select_query, &mut database, &mut ndjson_writer).unwrap();
+            progress_writer
+        }
+
+        // Each of the 1000 labels is written right after it is decoded, before the next one
+        let progress_writer = write_ndjson("SELECT label FROM numbers");
+        let decoded_values = progress_writer.decoded_values_at_flush;
+        assert_eq!(decoded_values.len(), 1000);
+        assert!(decoded_values.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert!(progress_writer
+            .bytes
+            .starts_with(b"{\"label\":\"number 1 of the table\"}\n"));
+
+        // Sorted rows are all read first
+        let progress_writer = write_ndjson("SELECT label FROM numbers ORDER BY label");
+        let decoded_values = progress_writer.decoded_values_at_flush;
+        assert_eq!(decoded_values.len(), 1000);
+        assert!(decoded_values.windows(2).all(|pair| pair[1] == pair[0]));
+    }
+
+    #[test]
+    fn upper_and_lower_convert_text_only() {
+        let mut database = open_fixture("sample.db");
+        assert_eq!(
+            query_rows(
+                &mut database,
+                "SELECT upper(name), lower(name) FROM apples WHERE id = 1"
+            ),
+            vec![vec![text("GRANNY SMITH"), text("granny smith")]]
+        );
+
+        // NULL, 42, 2.5, 'text' and x'00ff': values other than text keep their storage class
+        let mut database = open_fixture("types.db");
+        let rows = query_rows(
+            &mut database,
+            "SELECT upper(value), lower(value) FROM mixed WHERE id <= 5",
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Null, Value::Null],
+                vec![Value::Integer(42), Value::Integer(42)],
+                vec![Value::Real(2.5), Value::Real(2.5)],
+                vec![text("TEXT"), text("text")],
+                vec![Value::Blob(vec![0x00, 0xff]), Value::Blob(vec![0x00, 0xff])],
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::value::{as_real, to_numeric};
+use crate::Value;
+
+/// A scalar function of a SELECT item, applied to a single value per row.
+///
+/// Core functions doc: https://www.sqlite.org/lang_corefunc.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScalarFunction {
+    Typeof, // typeof(x): the storage class of x, e.g. 'integer'
+    Length, // length(x): the number of characters of x, or of bytes for a blob
+    Upper,  // upper(x): x in upper case
+    Lower,  // lower(x): x in lower case
+    Abs,    // abs(x): the absolute value of x
+}
+
+impl ScalarFunction {
+    /// Look up a function by name, in any case.
+    pub(crate) fn from_name(name: &str) -> Option<ScalarFunction> {
+        match name.to_ascii_lowercase().as_str() {
+            "typeof" => Some(ScalarFunction::Typeof),
+            "length" => Some(ScalarFunction::Length),
+            "upper" => Some(ScalarFunction::Upper),
+            "lower" => Some(ScalarFunction::Lower),
+            "abs" => Some(ScalarFunction::Abs),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn apply(self, value: Value) -> Value {
+        match self {
+            ScalarFunction::Typeof => {
+                let type_name = match value {
+                    Value::Null => "null",
+                    Value::Integer(_) => "integer",
+                    Value::Real(_) => "real",
+                    Value::Text(_) => "text",
+                    Value::Blob(_) => "blob",
+                };
+                Value::Text(type_name.to_string())
+            }
+            // Like SQLite, text stops at its first NUL character, and numbers are measured in
+            // their text form, e.g. length(2.50) is 3 for '2.5'
+            ScalarFunction::Length => match value {
+                Value::Null => Value::Null,
+                Value::Blob(bytes) => Value::Integer(bytes.len() as i64),
+                value => {
+                    let text = value.to_string();
+                    let nb_chars = text.chars().take_while(|&c| c != '\0').count();
+                    Value::Integer(nb_chars as i64)
+                }
+            },
+            // Like SQLite without the ICU extension, only ASCII letters change case. Values
+            // other than text are returned unchanged, e.g. upper(42) is the integer 42.
+            ScalarFunction::Upper => match value {
+                Value::Text(text) => Value::Text(text.to_ascii_uppercase()),
+                value => value,
+            },
+            ScalarFunction::Lower => match value {
+                Value::Text(text) => Value::Text(text.to_ascii_lowercase()),
+                value => value,
+            },
+            // Like SQLite, text and blobs are read as reals. SQLite fails on the absolute value of
+            // the smallest integer, which has no integer result: it is a real here.
+            ScalarFunction::Abs => match value {
+                Value::Null => Value::Null,
+                Value::Integer(n) => n
+                    .checked_abs()
+                    .map_or(Value::Real(-(n as f64)), Value::Integer),
+                Value::Real(x) => Value::Real(x.abs()),
+                value => Value::Real(as_real(&to_numeric(value)).abs()),
+            },
+        }
+    }
+}
+
+/// A binary arithmetic operator of a SELECT item, e.g. `price * 2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl ArithmeticOperator {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            ArithmeticOperator::Add => "+",
+            ArithmeticOperator::Subtract => "-",
+            ArithmeticOperator::Multiply => "*",
+            ArithmeticOperator::Divide => "/",
+        }
+    }
+
+    /// Operators binding tighter come first: `*` and `/` bind tighter than `+` and `-`.
+    pub(crate) fn precedence(self) -> u8 {
+        match self {
+            ArithmeticOperator::Multiply | ArithmeticOperator::Divide => 0,
+            ArithmeticOperator::Add | ArithmeticOperator::Subtract => 1,
+        }
+    }
+
+    /// Apply the operator the way SQLite does:
+    /// - NULL operands give NULL
+    /// - text and blob operands are read as numbers first, see `to_numeric`
+    /// - integer operands give an integer, unless the result overflows: it is a real then
+    /// - integer division truncates towards zero, and a division by zero gives NULL
+    pub(crate) fn apply(self, left: Value, right: Value) -> Value {
+        let (left, right) = (to_numeric(left), to_numeric(right));
+        match (left, right) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Integer(a), Value::Integer(b)) => {
+                let result = match self {
+                    ArithmeticOperator::Add => a.checked_add(b),
+                    ArithmeticOperator::Subtract => a.checked_sub(b),
+                    ArithmeticOperator::Multiply => a.checked_mul(b),
+                    ArithmeticOperator::Divide if b == 0 => return Value::Null,
+                    ArithmeticOperator::Divide => a.checked_div(b),
+                };
+                result.map_or_else(|| self.apply_real(a as f64, b as f64), Value::Integer)
+            }
+            (a, b) => self.apply_real(as_real(&a), as_real(&b)),
+        }
+    }
+
+    fn apply_real(self, a: f64, b: f64) -> Value {
+        match self {
+            ArithmeticOperator::Add => Value::Real(a + b),
+            ArithmeticOperator::Subtract => Value::Real(a - b),
+            ArithmeticOperator::Multiply => Value::Real(a * b),
+            ArithmeticOperator::Divide if b == 0.0 => Value::Null,
+            ArithmeticOperator::Divide => Value::Real(a / b),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{prelude::*, SeekFrom};
+
+use crate::database::DB_HEADER_SIZE;
+use crate::{SQLiteError, SQLiteInternalError};
+
+/// The magic string at the start of every SQLite database file.
+const MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+const MIN_PAGE_SIZE: u64 = 512;
+
+/// How text values are encoded in the database, for the whole file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+impl TextEncoding {
+    /// Look up an encoding by name, as given to `PRAGMA encoding`: 'UTF-8', 'UTF-16le' or
+    /// 'UTF-16be', in any case and with or without the dash.
+    pub fn from_name(name: &str) -> Option<TextEncoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(TextEncoding::Utf8),
+            "utf-16le" | "utf16le" => Some(TextEncoding::Utf16le),
+            "utf-16be" | "utf16be" => Some(TextEncoding::Utf16be),
+            _ => None,
+        }
+    }
+}
+
+/// The database header: the first 100 bytes of the file.
+///
+/// Header format doc: https://www.sqlite.org/fileformat2.html#the_database_header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbHeader {
+    /// 'SQLite format 3' followed by a nul byte
+    pub magic: [u8; 16],
+    /// Size of a page in bytes, a power of two between 512 and 65536
+    pub page_size: u32,
+    /// 1 for the legacy rollback journal, 2 for WAL
+    pub write_version: u8,
+    pub read_version: u8,
+    /// Bytes reserved at the end of each page, e.g. by encryption extensions
+    pub reserved_space: u8,
+    /// Payload fractions, must be 64, 32 and 32
+    pub max_embedded_payload_fraction: u8,
+    pub min_embedded_payload_fraction: u8,
+    pub leaf_payload_fraction: u8,
+    pub file_change_counter: u32,
+    /// Size of the database in pages, only valid if `version_valid_for` matches
+    /// `file_change_counter`
+    pub page_count: u32,
+    pub first_freelist_trunk_page: u32,
+    pub freelist_page_count: u32,
+    pub schema_cookie: u32,
+    /// 1 to 4
+    pub schema_format: u32,
+    pub default_page_cache_size: u32,
+    /// Largest root b-tree page in auto-vacuum or incremental-vacuum modes, 0 otherwise
+    pub largest_root_page: u32,
+    /// 1 for UTF-8, 2 for UTF-16le, 3 for UTF-16be
+    pub text_encoding: u32,
+    /// Free for applications to use, e.g. as a schema version. Set with 'PRAGMA user_version'.
+    pub user_version: u32,
+    /// Non-zero for incremental-vacuum mode
+    pub incremental_vacuum: u32,
+    /// Identifies the application owning the file, set with 'PRAGMA application_id'
+    pub application_id: u32,
+    pub version_valid_for: u32,
+    /// Version of SQLite that last modified the file, e.g. 3046001 for 3.46.1
+    pub sqlite_version_number: u32,
+}
+
+impl DbHeader {
+    /// Parse the database header from its bytes. The magic string and the page size are
+    /// validated.
+    pub fn parse(bytes: &[u8; DB_HEADER_SIZE as usize]) -> Result<DbHeader, SQLiteError> {
+        Ok(parse_db_header(bytes)?)
+    }
+
+    /// Read the database header, at the start of the file.
+    ///
+    /// Encrypted databases (e.g. with SEE or SQLCipher) have no readable header, not even the
+    /// magic string, but are still made of whole pages: a file without the magic string whose
+    /// size is a multiple of the smallest page size is reported as possibly encrypted.
+    pub(crate) fn read(db: &mut (impl Read + Seek)) -> Result<DbHeader, SQLiteInternalError> {
+        let file_size = db
+            .seek(SeekFrom::End(0))
+            .map_err(SQLiteInternalError::SeekError)?;
+        if file_size < DB_HEADER_SIZE {
+            return Err(SQLiteInternalError::NotADatabase);
+        }
+
+        let mut bytes = [0; DB_HEADER_SIZE as usize];
+        db.seek(SeekFrom::Start(0))
+            .map_err(SQLiteInternalError::SeekError)?;
+        db.read_exact(&mut bytes)
+            .map_err(SQLiteInternalError::ReadError)?;
+        match parse_db_header(&bytes) {
+            Err(SQLiteInternalError::NotADatabase) if file_size % MIN_PAGE_SIZE == 0 => {
+                Err(SQLiteInternalError::PossiblyEncrypted)
+            }
+            db_header => db_header,
+        }
+    }
+
+    /// The text encoding of the database, or `None` if the header holds an unknown value.
+    pub fn encoding(&self) -> Option<TextEncoding> {
+        match self.text_encoding {
+            1 => Some(TextEncoding::Utf8),
+            2 => Some(TextEncoding::Utf16le),
+            3 => Some(TextEncoding::Utf16be),
+            _ => None,
+        }
+    }
+
+    /// Whether the in-header page count can be trusted: older versions of SQLite did not keep it
+    /// up to date, in which case they did not update 'version-valid-for' either.
+    pub fn is_page_count_valid(&self) -> bool {
+        self.page_count != 0 && self.version_valid_for == self.file_change_counter
+    }
+}
+
+fn parse_db_header(bytes: &[u8; DB_HEADER_SIZE as usize]) -> Result<DbHeader, SQLiteInternalError> {
+    if bytes[..16] != MAGIC[..] {
+        return Err(SQLiteInternalError::NotADatabase);
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_be_bytes(
+            bytes[offset..offset + 4]
+                .try_into()
+                .expect("slice should have 4 bytes"),
+        )
+    };
+
+    // 'The page size for a database file is determined by the 2-byte integer located at an offset
+    // of 16 bytes from the beginning of the database file.' The value 1 stands for 65536.
+    let page_size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+        1 => 65536,
+        page_size if u64::from(page_size) >= MIN_PAGE_SIZE && page_size.is_power_of_two() => {
+            u32::from(page_size)
+        }
+        page_size => return Err(SQLiteInternalError::InvalidPageSize(page_size)),
+    };
+
+    Ok(DbHeader {
+        magic: *MAGIC,
+        page_size,
+        write_version: bytes[18],
+        read_version: bytes[19],
+        reserved_space: bytes[20],
+        max_embedded_payload_fraction: bytes[21],
+        min_embedded_payload_fraction: bytes[22],
+        leaf_payload_fraction: bytes[23],
+        file_change_counter: read_u32(24),
+        page_count: read_u32(28),
+        first_freelist_trunk_page: read_u32(32),
+        freelist_page_count: read_u32(36),
+        schema_cookie: read_u32(40),
+        schema_format: read_u32(44),
+        default_page_cache_size: read_u32(48),
+        largest_root_page: read_u32(52),
+        text_encoding: read_u32(56),
+        user_version: read_u32(60),
+        incremental_vacuum: read_u32(64),
+        application_id: read_u32(68),
+        // bytes 72 to 91 are reserved for expansion
+        version_valid_for: read_u32(92),
+        sqlite_version_number: read_u32(96),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::{fixture_bytes, open_fixture};
+
+    /// The header of 'sample.db', with the given 2-byte page size field.
+    fn header_with_page_size(page_size: u16) -> [u8; DB_HEADER_SIZE as usize] {
+        let mut bytes: [u8; DB_HEADER_SIZE as usize] = fixture_bytes("sample.db")
+            [..DB_HEADER_SIZE as usize]
+            .try_into()
+            .unwrap();
+        bytes[16..18].copy_from_slice(&page_size.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn header_of_sample_db() {
+        let header: [u8; DB_HEADER_SIZE as usize] = fixture_bytes("sample.db")
+            [..DB_HEADER_SIZE as usize]
+            .try_into()
+            .unwrap();
+        let db_header = DbHeader::parse(&header).unwrap();
+        assert_eq!(&db_header.magic, MAGIC);
+        assert_eq!(db_header.page_size, 4096);
+        assert_eq!((db_header.write_version, db_header.read_version), (1, 1));
+        assert_eq!(db_header.reserved_space, 0);
+        assert_eq!(db_header.file_change_counter, 5);
+        assert_eq!(db_header.page_count, 4);
+        assert_eq!(db_header.schema_cookie, 2);
+        assert_eq!(db_header.schema_format, 4);
+        assert_eq!(db_header.text_encoding, 1);
+        assert_eq!(db_header.user_version, 0);
+        assert_eq!(db_header.sqlite_version_number, 3034000);
+    }
+
+    #[test]
+    fn user_version_and_application_id_are_read() {
+        let db_header = open_fixture("versioned.db").header().unwrap();
+        assert_eq!(db_header.user_version, 7);
+        assert_eq!(db_header.application_id, 1936024932);
+    }
+
+    #[test]
+    fn page_size_is_a_power_of_two() {
+        assert_eq!(
+            DbHeader::parse(&header_with_page_size(4096))
+                .unwrap()
+                .page_size,
+            4096
+        );
+        // 65536 does not fit in 2 bytes: it is written as 1
+        assert_eq!(
+            DbHeader::parse(&header_with_page_size(1))
+                .unwrap()
+                .page_size,
+            65536
+        );
+        assert!(matches!(
+            DbHeader::parse(&header_with_page_size(3000)),
+            Err(SQLiteError::InternalError(
+                SQLiteInternalError::InvalidPageSize(3000)
+            ))
+        ));
+    }
+
+    /// Deterministic pseudo-random bytes, from a linear congruential generator.
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn random_whole_pages_are_possibly_encrypted() {
+        assert!(matches!(
+            DbHeader::read(&mut Cursor::new(random_bytes(4 * 4096))),
+            Err(SQLiteInternalError::PossiblyEncrypted)
+        ));
+        // Not a whole number of pages
+        assert!(matches!(
+            DbHeader::read(&mut Cursor::new(random_bytes(1000))),
+            Err(SQLiteInternalError::NotADatabase)
+        ));
+    }
+}
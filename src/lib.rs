@@ -0,0 +1,42 @@
+//! A barebones SQLite implementation: reads a database file and answers a small subset of SQL.
+//!
+//! SQLite file format doc: https://www.sqlite.org/fileformat2.html
+
+mod btree;
+mod database;
+mod error;
+mod exec;
+mod function;
+mod header;
+mod output;
+mod page;
+mod pattern;
+mod planner;
+mod query;
+mod record;
+mod schema;
+mod table;
+#[cfg(test)]
+mod testing;
+mod tokenizer;
+mod value;
+mod varint;
+
+pub use btree::{PageInfo, PageType};
+pub use database::{Database, PageCount};
+pub use error::{
+    SQLQueryError, SQLQueryParsingError, SQLiteError, SQLiteInternalError, SerialTypeError,
+};
+pub use exec::{ColumnMeta, ResultSet};
+pub use header::{DbHeader, TextEncoding};
+pub use output::{OutputConfig, OutputMode};
+pub use page::Page;
+pub use query::{
+    parse_create_table, parse_sql_query, parse_sql_query_with_parameters, ColumnDef,
+    CountNonNullQueryData, CreateTable, OrderingTerm, SQLQuery, SelectQueryData, UnionAllQueryData,
+};
+pub use schema::{ObjectType, SchemaRow, Stat1Row};
+pub use table::{Row, Table};
+pub use tokenizer::TokenizerError;
+pub use value::Value;
+pub use varint::{decode_signed_varint, decode_varint};
@@ -0,0 +1,318 @@
+use std::io::{self, prelude::*};
+
+use crate::exec::{ColumnMeta, ResultSet, RowSink};
+use crate::Value;
+
+/// Settings controlling how query results are printed.
+#[derive(Debug, Default)]
+pub struct OutputConfig {
+    pub show_rowid: bool, // prefix each row with its rowid, tab-separated or as a first column
+    pub mode: OutputMode,
+    pub show_headers: bool, // print the column names before the rows, set by '.headers'
+    /// Column widths in column mode, set by '.width'. 0 means auto, a negative width
+    /// right-justifies the column. Columns past the end of the list are auto.
+    pub column_widths: Vec<i64>,
+    /// Maximum number of rows printed per query, set by '.maxrows'. None means unlimited.
+    pub max_rows: Option<usize>,
+}
+
+/// Output modes, as set by '.mode'.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Values separated by '|', one row per line
+    #[default]
+    List,
+    /// Comma-separated values, one row per line, text quoted when needed
+    Csv,
+    /// Left-aligned columns under a header, separated by two spaces
+    Column,
+    /// One JSON object per row and per line, mapping column names to values. Each line is
+    /// flushed as soon as it is written. Unless a SELECT sorts or aggregates its rows, they are
+    /// written as the table scan reads them, for consumers to process rows as they come.
+    Ndjson,
+    /// A JSON array of row objects, like sqlite3's JSON mode: one row per line, nothing at all
+    /// for an empty result.
+    Json,
+}
+
+/// Print result rows to stdout, see `write_rows`, up to the maximum number of rows if any. A
+/// closed stdout, e.g. piped into `head`, ends the output.
+pub(crate) fn print_rows(mut result_set: ResultSet, output_config: &OutputConfig) {
+    let nb_rows = result_set.rows.len();
+    if let Some(max_rows) = output_config.max_rows {
+        result_set.truncate(max_rows);
+    }
+    let _ = write_rows(&mut std::io::stdout().lock(), &result_set, output_config);
+    // The note goes to stderr, not to mix with the rows
+    if result_set.rows.len() < nb_rows {
+        eprintln!(
+            "... output truncated: {} of {} rows shown (see .maxrows)",
+            result_set.rows.len(),
+            nb_rows
+        );
+    }
+}
+
+/// Writes the rows of a SELECT query as ndjson lines as soon as it produces them, up to the maximum
+/// number of rows if any. Like `print_rows`, a closed output ends the lines.
+pub(crate) struct NdjsonWriter<W: Write> {
+    out: W,
+    col_names: Vec<String>,
+    max_rows: Option<usize>,
+    nb_rows: usize, // rows pushed so far, written or not
+    is_closed: bool,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub(crate) fn new(out: W, max_rows: Option<usize>) -> Self {
+        NdjsonWriter {
+            out,
+            col_names: Vec::new(),
+            max_rows,
+            nb_rows: 0,
+            is_closed: false,
+        }
+    }
+
+    /// Note to stderr, once the query is done, how many rows were left out by '.maxrows'.
+    pub(crate) fn finish(self) {
+        if let Some(max_rows) = self.max_rows.filter(|&max_rows| max_rows < self.nb_rows) {
+            eprintln!(
+                "... output truncated: {max_rows} of {} rows shown (see .maxrows)",
+                self.nb_rows
+            );
+        }
+    }
+}
+
+impl<W: Write> RowSink for NdjsonWriter<W> {
+    fn set_columns(&mut self, columns: Vec<ColumnMeta>) {
+        self.col_names = columns.into_iter().map(|column| column.name).collect();
+    }
+
+    fn push_row(&mut self, _rowid: i64, row: Vec<Value>) {
+        self.nb_rows += 1;
+        if self.is_closed
+            || self
+                .max_rows
+                .is_some_and(|max_rows| self.nb_rows > max_rows)
+        {
+            return;
+        }
+        let written = writeln!(self.out, "{}", json_object(&self.col_names, &row))
+            .and_then(|()| self.out.flush());
+        self.is_closed = written.is_err();
+    }
+}
+
+/// Write result rows. Each row may carry the rowid of the table row it comes from.
+///
+/// Whatever the mode, every line ends with a single '\n', the last one included, and nothing
+/// follows it: an empty result writes nothing at all, unless the mode prints a header for it.
+pub(crate) fn write_rows(
+    out: &mut impl Write,
+    result_set: &ResultSet,
+    output_config: &OutputConfig,
+) -> io::Result<()> {
+    let col_names: &[String] = &result_set
+        .columns
+        .iter()
+        .map(|column| column.name.clone())
+        .collect::<Vec<_>>();
+    let rows: &[(Option<i64>, &Vec<Value>)] = &result_set
+        .rowids
+        .iter()
+        .copied()
+        .zip(&result_set.rows)
+        .collect::<Vec<_>>();
+    let rowid_prefix = |rowid: Option<i64>| match rowid {
+        Some(rowid) if output_config.show_rowid => format!("{rowid}\t"),
+        _ => String::new(),
+    };
+
+    match output_config.mode {
+        OutputMode::List => {
+            if output_config.show_headers && !rows.is_empty() {
+                writeln!(out, "{}", col_names.join("|"))?;
+            }
+            for (rowid, row) in rows {
+                let formatted_values = row.iter().map(Value::to_string).collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "{}{}",
+                    rowid_prefix(*rowid),
+                    formatted_values.join("|")
+                )?;
+            }
+        }
+        OutputMode::Csv => {
+            if output_config.show_headers && !rows.is_empty() {
+                let quoted_names = col_names
+                    .iter()
+                    .map(|col_name| csv_field(col_name))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}", quoted_names.join(","))?;
+            }
+            for (rowid, row) in rows {
+                let fields = row
+                    .iter()
+                    .map(|value| match value {
+                        Value::Text(text) => csv_field(text),
+                        Value::Blob(bytes) => csv_field(&String::from_utf8_lossy(bytes)),
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}{}", rowid_prefix(*rowid), fields.join(","))?;
+            }
+        }
+        OutputMode::Ndjson => {
+            for (_, row) in rows {
+                writeln!(out, "{}", json_object(col_names, row))?;
+                out.flush()?;
+            }
+        }
+        OutputMode::Json => {
+            let objects = rows
+                .iter()
+                .map(|(_, row)| json_object(col_names, row))
+                .collect::<Vec<_>>();
+            if !objects.is_empty() {
+                writeln!(out, "[{}]", objects.join(",\n"))?;
+            }
+        }
+        OutputMode::Column => {
+            // Like sqlite3, nothing is printed for an empty result, not even the header. The header
+            // is always printed otherwise.
+            if rows.is_empty() {
+                return Ok(());
+            }
+
+            let formatted_rows = rows
+                .iter()
+                .map(|(_, row)| row.iter().map(Value::to_string).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let widths = (0..col_names.len())
+                .map(|col_idx| {
+                    match output_config.column_widths.get(col_idx) {
+                        Some(&width) if width != 0 => width,
+                        // Auto: fit the widest of the header and the values
+                        _ => formatted_rows
+                            .iter()
+                            .map(|row| row[col_idx].chars().count())
+                            .chain([col_names[col_idx].chars().count()])
+                            .max()
+                            .unwrap_or_default() as i64,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // With rowids shown, they make a first column, for the header and the separator to
+            // stay aligned with the values
+            let rowid_width = rows
+                .iter()
+                .filter(|_| output_config.show_rowid)
+                .filter_map(|(rowid, _)| rowid.map(|rowid| rowid.to_string().len()))
+                .max()
+                .map(|max_len| max_len.max("rowid".len()));
+            let line_prefix = |rowid_cell: &str| match rowid_width {
+                Some(width) => format!("{}  ", pad(rowid_cell, width as i64)),
+                None => String::new(),
+            };
+
+            let mut write_line = |prefix: &str, cells: &[String]| {
+                let padded_cells = cells
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, &width)| pad(cell, width))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{prefix}{}", padded_cells.join("  "))
+            };
+
+            write_line(&line_prefix("rowid"), col_names)?;
+            let dashes = widths
+                .iter()
+                .map(|width| "-".repeat(width.unsigned_abs() as usize))
+                .collect::<Vec<_>>();
+            let rowid_dashes = "-".repeat(rowid_width.unwrap_or_default());
+            write_line(&line_prefix(&rowid_dashes), &dashes)?;
+            for ((rowid, _), formatted_row) in rows.iter().zip(&formatted_rows) {
+                let rowid_cell = rowid.map(|rowid| rowid.to_string()).unwrap_or_default();
+                write_line(&line_prefix(&rowid_cell), formatted_row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Quote a CSV field like sqlite3 does: only when it is empty, or holds a space, a comma, a quote,
+/// a control or a non-ASCII character. Double quotes are doubled.
+fn csv_field(text: &str) -> String {
+    let needs_quotes = text.is_empty()
+        || text
+            .chars()
+            .any(|c| matches!(c, ' ' | ',' | '"' | '\'') || c.is_control() || !c.is_ascii());
+    if needs_quotes {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Write a row as a JSON object, like sqlite3's JSON mode does: numbers as JSON numbers, blobs as
+/// strings of their bytes.
+fn json_object(col_names: &[String], row: &[Value]) -> String {
+    let members = col_names
+        .iter()
+        .zip(row)
+        .map(|(col_name, value)| format!("{}:{}", json_string(col_name), json_value(value)))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", members.join(","))
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Integer(n) => n.to_string(),
+        // JSON has no infinity
+        Value::Real(x) if x.is_infinite() => "null".to_string(),
+        Value::Real(_) => value.to_string(),
+        Value::Text(text) => json_string(text),
+        Value::Blob(bytes) => json_string(
+            &bytes
+                .iter()
+                .map(|&byte| char::from(byte))
+                .collect::<String>(),
+        ),
+    }
+}
+
+/// Quote a string for JSON, escaping quotes, backslashes and control characters.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Fit a cell to a column width: truncated if too long, then padded with spaces on the right, or
+/// on the left for a negative (right-justified) width.
+fn pad(cell: &str, width: i64) -> String {
+    let len = width.unsigned_abs() as usize;
+    let cell = cell.chars().take(len).collect::<String>();
+    if width < 0 {
+        format!("{cell:>len$}")
+    } else {
+        format!("{cell:<len$}")
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::io::{prelude::*, SeekFrom};
+
+use crate::btree::{
+    get_cell_ptr_array, read_page_header, read_table_leaf_cell, PageLayout, PageType,
+};
+use crate::record::parse_record;
+use crate::varint::decode_varint;
+use crate::{SQLiteError, SQLiteInternalError, TextEncoding, Value};
+
+/// A b-tree page, read whole into memory: its cells are decoded from the page buffer, without
+/// going back to the file.
+///
+/// The only exception are the payloads too large for the page, which spill onto overflow pages:
+/// those are read along with the page.
+#[derive(Debug, Clone)]
+pub struct Page {
+    page_number: u32,
+    page_type: PageType,
+    bytes: Vec<u8>,
+    cell_offsets: Vec<u16>, // relative to the start of the page, in cell order
+    overflow_payloads: HashMap<usize, Vec<u8>>, // by cell index
+    encoding: TextEncoding,
+}
+
+impl Page {
+    /// Read a b-tree page, and the payloads of its cells spilling onto overflow pages, if any.
+    pub(crate) fn read(
+        page_number: u32,
+        page_layout: PageLayout,
+        encoding: TextEncoding,
+        db: &mut (impl Read + Seek),
+    ) -> Result<Page, SQLiteInternalError> {
+        let page_offset = page_layout.seek_btree_page(page_number, db)?;
+        let page_header = read_page_header(db)?;
+        let cell_offsets = get_cell_ptr_array(page_header.nb_cells, db)?;
+
+        db.seek(SeekFrom::Start(page_offset))
+            .map_err(SQLiteInternalError::SeekError)?;
+        let mut bytes = vec![0; page_layout.page_size as usize];
+        db.read_exact(&mut bytes)
+            .map_err(SQLiteInternalError::ReadError)?;
+
+        let mut overflow_payloads = HashMap::new();
+        if page_header.page_type == PageType::LeafTable {
+            // 'X is U-35 for table-btree leaf pages'
+            let max_local = page_layout.usable_size - 35;
+            for (cell_idx, &cell_offset) in cell_offsets.iter().enumerate() {
+                let (payload_size, _) = bytes
+                    .get(usize::from(cell_offset)..)
+                    .and_then(decode_varint)
+                    .ok_or(SQLiteInternalError::MalformedRecord)?;
+                if payload_size > max_local {
+                    let (_rowid, payload) =
+                        read_table_leaf_cell(page_offset, cell_offset, page_layout, db)?;
+                    overflow_payloads.insert(cell_idx, payload);
+                }
+            }
+        }
+
+        Ok(Page {
+            page_number,
+            page_type: page_header.page_type,
+            bytes,
+            cell_offsets,
+            overflow_payloads,
+            encoding,
+        })
+    }
+
+    pub fn page_number(&self) -> u32 {
+        self.page_number
+    }
+
+    pub fn page_type(&self) -> PageType {
+        self.page_type
+    }
+
+    pub fn nb_cells(&self) -> usize {
+        self.cell_offsets.len()
+    }
+
+    /// Decode the record of each cell of a leaf table page, i.e. the values of its rows, in rowid
+    /// order. Like in the file, the value of an 'INTEGER PRIMARY KEY' column is NULL, and columns
+    /// added after a row was written are missing from its record.
+    ///
+    /// Other pages have no rows: a single error is returned for them.
+    pub fn records(&self) -> impl Iterator<Item = Result<Vec<Value>, SQLiteError>> + '_ {
+        let page_type_error = (self.page_type != PageType::LeafTable)
+            .then(|| Err(SQLiteInternalError::UnexpectedPageType(self.page_type).into()));
+        let nb_cells = if page_type_error.is_some() {
+            0
+        } else {
+            self.nb_cells()
+        };
+        page_type_error
+            .into_iter()
+            .chain((0..nb_cells).map(|cell_idx| Ok(self.record(cell_idx)?)))
+    }
+
+    /// Decode the record of a cell of a leaf table page, which holds:
+    /// - the payload size (varint)
+    /// - the rowid (varint)
+    /// - the payload: the record
+    fn record(&self, cell_idx: usize) -> Result<Vec<Value>, SQLiteInternalError> {
+        if let Some(payload) = self.overflow_payloads.get(&cell_idx) {
+            return parse_record(payload, self.encoding);
+        }
+
+        let cell = self
+            .bytes
+            .get(usize::from(self.cell_offsets[cell_idx])..)
+            .ok_or(SQLiteInternalError::MalformedRecord)?;
+        let (payload_size, payload_size_varint) =
+            decode_varint(cell).ok_or(SQLiteInternalError::MalformedRecord)?;
+        let (_rowid, rowid_varint) = decode_varint(&cell[payload_size_varint..])
+            .ok_or(SQLiteInternalError::MalformedRecord)?;
+        let payload_start = payload_size_varint + rowid_varint;
+        let payload = usize::try_from(payload_size)
+            .ok()
+            .and_then(|payload_size| cell.get(payload_start..payload_start + payload_size))
+            .ok_or(SQLiteInternalError::MalformedRecord)?;
+        parse_record(payload, self.encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{open_fixture, text};
+    use crate::{PageType, SQLiteError, SQLiteInternalError, Value};
+
+    #[test]
+    fn records_of_a_leaf_table_page() {
+        let mut database = open_fixture("sample.db");
+        // 'apples', a single leaf page
+        let page = database.page(2).unwrap();
+        assert_eq!(page.page_type(), PageType::LeafTable);
+        assert_eq!(page.nb_cells(), 4);
+
+        let records = page.records().collect::<Result<Vec<_>, _>>().unwrap();
+        let row = |name: &str, color: &str| vec![Value::Null, text(name), text(color)];
+        assert_eq!(
+            records,
+            vec![
+                row("Granny Smith", "Light Green"),
+                row("Fuji", "Red"),
+                row("Honeycrisp", "Blush Red"),
+                row("Golden Delicious", "Yellow"),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_of_an_interior_page_is_a_single_error() {
+        let mut database = open_fixture("many_tables.db");
+        let page = database.page(1).unwrap();
+        assert_eq!(page.page_type(), PageType::InteriorTable);
+
+        let records = page.records().collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert!(matches!(
+            records[0],
+            Err(SQLiteError::InternalError(
+                SQLiteInternalError::UnexpectedPageType(PageType::InteriorTable)
+            ))
+        ));
+    }
+}
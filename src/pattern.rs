@@ -0,0 +1,133 @@
+/// Match text against a LIKE pattern: '%' matches any sequence of characters, '_' any single
+/// character. Like SQLite, the match ignores the case of ASCII letters, not of other characters.
+///
+/// The escape character, if any, makes the character following it in the pattern match literally,
+/// e.g. with '\' as the escape, '100\%' only matches '100%'.
+pub(crate) fn like(pattern: &str, text: &str, escape: Option<char>) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    like_chars(&pattern, &text, escape)
+}
+
+fn like_chars(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [c, rest @ ..] if Some(*c) == escape => match (rest, text) {
+            ([literal, rest @ ..], [t, text_rest @ ..]) if literal.eq_ignore_ascii_case(t) => {
+                like_chars(rest, text_rest, escape)
+            }
+            // An escape must be followed by the character it escapes
+            _ => false,
+        },
+        ['%', rest @ ..] => {
+            (0..=text.len()).any(|skipped| like_chars(rest, &text[skipped..], escape))
+        }
+        ['_', rest @ ..] => !text.is_empty() && like_chars(rest, &text[1..], escape),
+        [c, rest @ ..] => match text {
+            [t, text_rest @ ..] if c.eq_ignore_ascii_case(t) => like_chars(rest, text_rest, escape),
+            _ => false,
+        },
+    }
+}
+
+/// Match text against a GLOB pattern, with the Unix file name wildcards: '*' matches any sequence
+/// of characters, '?' any single character, and '[...]' any character of a set. Unlike LIKE, the
+/// match is case-sensitive.
+///
+/// A set may hold ranges like 'a-z', and is negated by a leading '^'. A ']' right after the
+/// opening bracket (or the '^') is part of the set, as is a '-' at its start or end, e.g. '[]-]'
+/// matches ']' or '-'. An unclosed set never matches.
+pub(crate) fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    glob_chars(&pattern, &text)
+}
+
+fn glob_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', rest @ ..] => (0..=text.len()).any(|skipped| glob_chars(rest, &text[skipped..])),
+        ['?', rest @ ..] => !text.is_empty() && glob_chars(rest, &text[1..]),
+        ['[', set @ ..] => match (match_set(set, text.first()), text) {
+            (Some((true, rest)), [_, text_rest @ ..]) => glob_chars(rest, text_rest),
+            _ => false,
+        },
+        [c, rest @ ..] => match text {
+            [t, text_rest @ ..] if c == t => glob_chars(rest, text_rest),
+            _ => false,
+        },
+    }
+}
+
+/// Match a character against the set at the start of `set`, just after its '['.
+///
+/// Returns whether the character is matched, and the rest of the pattern after the closing ']'.
+/// Returns `None` if the set is not closed.
+fn match_set<'a>(set: &'a [char], c: Option<&char>) -> Option<(bool, &'a [char])> {
+    let (negated, mut set) = match set {
+        ['^', rest @ ..] => (true, rest),
+        set => (false, set),
+    };
+
+    let mut is_in_set = false;
+    let mut is_first = true;
+    loop {
+        match set {
+            [] => return None,
+            [']', rest @ ..] if !is_first => {
+                return Some((c.is_some() && is_in_set != negated, rest));
+            }
+            [low, '-', high, rest @ ..] if *high != ']' => {
+                is_in_set |= c.is_some_and(|c| (low..=high).contains(&c));
+                set = rest;
+            }
+            [member, rest @ ..] => {
+                is_in_set |= c == Some(member);
+                set = rest;
+            }
+        }
+        is_first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_wildcards_match_literally() {
+        assert!(like("100\\%", "100%", Some('\\')));
+        assert!(!like("100\\%", "1000", Some('\\')));
+        assert!(like("%\\%%", "a 50% discount", Some('\\')));
+        assert!(!like("%\\%%", "a half discount", Some('\\')));
+        assert!(like("a!_b", "a_b", Some('!')));
+        assert!(!like("a!_b", "axb", Some('!')));
+        // Without an escape character, '%' is a wildcard
+        assert!(like("100%", "1000", None));
+    }
+
+    #[test]
+    fn glob_is_case_sensitive_unlike_like() {
+        assert!(glob("A*", "Apple"));
+        assert!(!glob("A*", "apple"));
+        assert!(like("A%", "apple", None));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_character() {
+        assert!(glob("b?t", "bat"));
+        assert!(!glob("b?t", "bt"));
+        assert!(!glob("b?t", "boot"));
+        // '_' is not a wildcard for GLOB, and '?' is not one for LIKE
+        assert!(!glob("b_t", "bat"));
+        assert!(!like("b?t", "bat", None));
+    }
+
+    #[test]
+    fn glob_sets_match_one_character_of_the_set() {
+        assert!(glob("[a-c]at", "bat"));
+        assert!(!glob("[a-c]at", "rat"));
+        assert!(glob("[^a-c]at", "rat"));
+        assert!(glob("[]]", "]"));
+    }
+}
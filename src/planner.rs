@@ -0,0 +1,317 @@
+use std::ops::RangeInclusive;
+
+use crate::query::{Comparison, Condition, Predicate};
+use crate::schema::{
+    indexed_col_names_from_sql_create_index_stmt, is_partial_index, ObjectType, SchemaRow,
+};
+use crate::Value;
+
+/// The value a WHERE condition checks: a column of the row, or its rowid.
+pub(crate) enum WhereColumn {
+    Column(usize), // index of the column in the record
+    Rowid,
+}
+
+/// How the rows of a table are read.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ScanPlan {
+    /// Read every row of the table b-tree, in rowid order
+    FullScan,
+    /// Read the rows whose rowid is in the range, through the table b-tree
+    RowidRange(RangeInclusive<i64>),
+    /// Seek the index for the entries whose leading columns equal `key`, then look up each row by
+    /// rowid: rows are visited in index order
+    IndexSeek {
+        index: IndexRef,
+        col_names: Vec<String>, // the pinned index columns
+        key: Vec<Value>,
+    },
+    /// Read the entries of the index instead of the rows, the index holding every needed value
+    IndexScan(IndexRef),
+}
+
+/// An index of the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexRef {
+    pub(crate) name: String,
+    pub(crate) root_page: u32,
+}
+
+impl ScanPlan {
+    /// Describe the plan for a table, in the words of sqlite3's EXPLAIN QUERY PLAN, e.g.
+    /// 'SEARCH apples USING INDEX idx_color (color=?)'.
+    pub(crate) fn describe(&self, table_name: &str) -> String {
+        match self {
+            ScanPlan::FullScan => format!("SCAN {table_name}"),
+            ScanPlan::RowidRange(range) => {
+                let bounds = if range.start() == range.end() {
+                    vec!["rowid=?"]
+                } else {
+                    [
+                        (*range.start() != i64::MIN).then_some("rowid>?"),
+                        (*range.end() != i64::MAX).then_some("rowid<?"),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+                };
+                format!(
+                    "SEARCH {table_name} USING INTEGER PRIMARY KEY ({})",
+                    bounds.join(" AND ")
+                )
+            }
+            ScanPlan::IndexSeek {
+                index, col_names, ..
+            } => {
+                let bounds = col_names
+                    .iter()
+                    .map(|col_name| format!("{col_name}=?"))
+                    .collect::<Vec<_>>();
+                format!(
+                    "SEARCH {table_name} USING INDEX {} ({})",
+                    index.name,
+                    bounds.join(" AND ")
+                )
+            }
+            ScanPlan::IndexScan(index) => {
+                format!("SCAN {table_name} USING COVERING INDEX {}", index.name)
+            }
+        }
+    }
+}
+
+/// Choose how to read the rows of a table matching the given WHERE conditions, each one checking
+/// the value of the matching `where_cols` item.
+///
+/// Conditions bounding the rowid lead straight to the matching rows, through the table b-tree.
+/// Otherwise, seek an index when the WHERE clause pins its leading columns, instead of scanning
+/// the whole table, like sqlite3 does.
+pub(crate) fn plan_scan(
+    schema_rows: &[SchemaRow],
+    table_name: &str,
+    conditions: &[&Condition],
+    where_cols: &[&WhereColumn],
+    rowid_alias_col_idx: Option<usize>,
+) -> ScanPlan {
+    if let Some(rowid_range) = rowid_range(conditions, where_cols, rowid_alias_col_idx) {
+        return ScanPlan::RowidRange(rowid_range);
+    }
+    match index_seek_key(schema_rows, table_name, conditions) {
+        Some((index, col_names, key)) => ScanPlan::IndexSeek {
+            index,
+            col_names,
+            key,
+        },
+        None => ScanPlan::FullScan,
+    }
+}
+
+/// Choose how to count the rows of a table.
+///
+/// An index holds one entry per table row, NULL keys included, and is usually much smaller than
+/// the table: its entries are counted instead, like sqlite3 does. A partial index only holds the
+/// rows matching its WHERE clause, so the table is counted when no other index is sure to cover
+/// every row.
+pub(crate) fn plan_count_rows(schema_rows: &[SchemaRow], table_name: &str) -> ScanPlan {
+    let index_row = schema_rows.iter().find(|&r| {
+        r.object_type == ObjectType::Index && r.tbl_name == table_name && !is_partial_index(&r.sql)
+    });
+    match index_row {
+        Some(index_row) => ScanPlan::IndexScan(IndexRef {
+            name: index_row.name.clone(),
+            root_page: index_row.root_page,
+        }),
+        None => ScanPlan::FullScan,
+    }
+}
+
+/// Choose how to count the non-NULL values of a table column.
+///
+/// An index whose first key column is the target column holds one entry per table row, and is
+/// usually much smaller than the table: its keys are counted instead of scanning the table.
+pub(crate) fn plan_count_non_null(
+    schema_rows: &[SchemaRow],
+    table_name: &str,
+    column_name: &str,
+) -> ScanPlan {
+    let index_row = schema_rows.iter().find(|&r| {
+        r.object_type == ObjectType::Index
+            && r.tbl_name == table_name
+            && indexed_col_names_from_sql_create_index_stmt(&r.sql)
+                .first()
+                .is_some_and(|indexed_col| indexed_col == column_name)
+    });
+    match index_row {
+        Some(index_row) => ScanPlan::IndexScan(IndexRef {
+            name: index_row.name.clone(),
+            root_page: index_row.root_page,
+        }),
+        None => ScanPlan::FullScan,
+    }
+}
+
+/// Find the range of rowids allowed by the `<rowid> <op> <integer>` conditions, where the rowid
+/// may also be named by its alias column. Returns `None` if no condition bounds the rowid.
+fn rowid_range(
+    conditions: &[&Condition],
+    where_cols: &[&WhereColumn],
+    rowid_alias_col_idx: Option<usize>,
+) -> Option<RangeInclusive<i64>> {
+    let mut rowid_range = None;
+    for (condition, where_col) in conditions.iter().zip(where_cols) {
+        let is_rowid = match where_col {
+            WhereColumn::Rowid => true,
+            WhereColumn::Column(col_idx) => rowid_alias_col_idx == Some(*col_idx),
+        };
+        if !is_rowid {
+            continue;
+        }
+
+        let (low, high) = match condition.predicate {
+            Predicate::Eq(Value::Integer(n)) => (n, n),
+            Predicate::Compare(Comparison::Ge, Value::Integer(n)) => (n, i64::MAX),
+            Predicate::Compare(Comparison::Le, Value::Integer(n)) => (i64::MIN, n),
+            // An out of range bound matches nothing: an empty range
+            Predicate::Compare(Comparison::Gt, Value::Integer(n)) => match n.checked_add(1) {
+                Some(low) => (low, i64::MAX),
+                None => (i64::MAX, i64::MIN),
+            },
+            Predicate::Compare(Comparison::Lt, Value::Integer(n)) => match n.checked_sub(1) {
+                Some(high) => (i64::MIN, high),
+                None => (i64::MAX, i64::MIN),
+            },
+            _ => continue,
+        };
+        let (previous_low, previous_high) = rowid_range
+            .map(|range: RangeInclusive<i64>| range.into_inner())
+            .unwrap_or((i64::MIN, i64::MAX));
+        rowid_range = Some(previous_low.max(low)..=previous_high.min(high));
+    }
+    rowid_range
+}
+
+/// Find an index of the table whose leading columns are all pinned by `<column> = <literal>`
+/// conditions. Returns the index covering the most columns, along with the pinned columns and the
+/// key to seek: the pinned values, in index column order.
+fn index_seek_key(
+    schema_rows: &[SchemaRow],
+    table_name: &str,
+    conditions: &[&Condition],
+) -> Option<(IndexRef, Vec<String>, Vec<Value>)> {
+    schema_rows
+        .iter()
+        .filter(|r| r.object_type == ObjectType::Index && r.tbl_name == table_name)
+        .map(|index_row| {
+            let (col_names, key) = indexed_col_names_from_sql_create_index_stmt(&index_row.sql)
+                .into_iter()
+                .map_while(|indexed_col| {
+                    conditions
+                        .iter()
+                        .find_map(|condition| match &condition.predicate {
+                            // Seeking NULL would find the NULL entries, which `= NULL` does not
+                            // match
+                            Predicate::Eq(value)
+                                if condition.column_name == indexed_col
+                                    && *value != Value::Null =>
+                            {
+                                Some(value.clone())
+                            }
+                            _ => None,
+                        })
+                        .map(|value| (indexed_col, value))
+                })
+                .unzip::<_, _, Vec<_>, Vec<_>>();
+            let index = IndexRef {
+                name: index_row.name.clone(),
+                root_page: index_row.root_page,
+            };
+            (index, col_names, key)
+        })
+        .filter(|(_, _, key)| !key.is_empty())
+        .max_by_key(|(_, _, key)| key.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{open_fixture, text};
+
+    fn eq(column_name: &str, value: Value) -> Condition {
+        Condition {
+            column_name: column_name.to_string(),
+            predicate: Predicate::Eq(value),
+        }
+    }
+
+    /// Plan a scan of 'fruits', whose columns are 'id', 'name' and 'color'.
+    fn plan_fruits_scan(conditions: &[Condition]) -> ScanPlan {
+        let schema_rows = open_fixture("misc.db").schema_rows().unwrap();
+        let where_cols = conditions
+            .iter()
+            .map(|condition| match condition.column_name.as_str() {
+                "name" => WhereColumn::Column(1),
+                _ => WhereColumn::Column(2),
+            })
+            .collect::<Vec<_>>();
+        plan_scan(
+            &schema_rows,
+            "fruits",
+            &conditions.iter().collect::<Vec<_>>(),
+            &where_cols.iter().collect::<Vec<_>>(),
+            Some(0),
+        )
+    }
+
+    #[test]
+    fn composite_index_is_sought_on_its_full_key() {
+        let plan = plan_fruits_scan(&[eq("name", text("cherry")), eq("color", text("red"))]);
+        assert!(matches!(
+            plan,
+            ScanPlan::IndexSeek { index, col_names, key }
+                if index.name == "idx_fruits_color_name"
+                    && col_names == ["color", "name"]
+                    && key == [text("red"), text("cherry")]
+        ));
+    }
+
+    #[test]
+    fn composite_index_is_sought_on_its_leading_column() {
+        let plan = plan_fruits_scan(&[eq("color", text("yellow"))]);
+        assert!(matches!(
+            plan,
+            ScanPlan::IndexSeek { col_names, key, .. }
+                if col_names == ["color"] && key == [text("yellow")]
+        ));
+
+        // No index starts with 'name'
+        assert_eq!(
+            plan_fruits_scan(&[eq("name", text("lime"))]),
+            ScanPlan::FullScan
+        );
+    }
+
+    #[test]
+    fn equality_on_an_indexed_column_seeks_the_index_and_anything_else_scans() {
+        // Both indexes of 'fruits' start with 'color'
+        let plan = plan_fruits_scan(&[eq("color", text("red"))]);
+        assert!(matches!(
+            &plan,
+            ScanPlan::IndexSeek { index, key, .. }
+                if index.name.starts_with("idx_fruits_color") && key == &[text("red")]
+        ));
+        assert!(plan
+            .describe("fruits")
+            .starts_with("SEARCH fruits USING INDEX idx_fruits_color"));
+
+        assert_eq!(plan_fruits_scan(&[]), ScanPlan::FullScan);
+        let like_red = Condition {
+            column_name: "color".to_string(),
+            predicate: Predicate::Like {
+                pattern: text("r%"),
+                escape: None,
+            },
+        };
+        assert_eq!(plan_fruits_scan(&[like_red]), ScanPlan::FullScan);
+        assert_eq!(ScanPlan::FullScan.describe("fruits"), "SCAN fruits");
+    }
+}
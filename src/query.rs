@@ -0,0 +1,1383 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::function::{ArithmeticOperator, ScalarFunction};
+use crate::tokenizer::{tokenize, Token, TokenKind};
+use crate::{SQLQueryParsingError, Value};
+
+#[derive(Debug)]
+pub enum SQLQuery {
+    CountRows(String), // count rows in a table. The string hold the table name.
+    CountNonNull(CountNonNullQueryData), // SELECT COUNT(color) FROM apples
+    Select(SelectQueryData), // SELECT name FROM apples
+    UnionAll(UnionAllQueryData), // SELECT name FROM apples UNION ALL SELECT name FROM oranges
+}
+
+impl FromStr for SQLQuery {
+    type Err = SQLQueryParsingError;
+
+    /// Parse a SQL query, see `parse_sql_query`.
+    fn from_str(sql_query: &str) -> Result<Self, Self::Err> {
+        parse_sql_query(sql_query)
+    }
+}
+
+#[derive(Debug)]
+pub struct CountNonNullQueryData {
+    pub(crate) table_name: String,
+    pub(crate) column_name: String,
+}
+
+impl CountNonNullQueryData {
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    pub fn column_name(&self) -> &str {
+        &self.column_name
+    }
+}
+
+#[derive(Debug)]
+pub struct SelectQueryData {
+    pub(crate) table_name: String,
+    pub(crate) select_items: Vec<SelectItem>,
+    pub(crate) where_clause: Option<WhereClause>,
+    pub(crate) group_by: Vec<String>, // grouping columns
+    pub(crate) having: Option<Having>,
+    pub(crate) order_by: Vec<OrderingTerm>, // sort keys, most significant first
+    pub(crate) limit: Option<usize>,        // maximum number of output rows, None for no limit
+}
+
+impl SelectQueryData {
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Names of the GROUP BY columns.
+    pub fn group_by(&self) -> &[String] {
+        &self.group_by
+    }
+
+    /// Sort keys of the ORDER BY clause, most significant first.
+    pub fn order_by(&self) -> &[OrderingTerm] {
+        &self.order_by
+    }
+
+    /// Maximum number of output rows, or `None` for no limit.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
+
+/// A sort key of an ORDER BY clause: a column, and its direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingTerm {
+    pub column_name: String,
+    pub descending: bool, // DESC, rather than the default ASC
+}
+
+/// SELECT queries whose rows are output one after the other, optionally sorted together.
+#[derive(Debug)]
+pub struct UnionAllQueryData {
+    pub(crate) selects: Vec<SelectQueryData>,
+    pub(crate) order_by: Vec<OrderingTerm>, // sort keys of the output, most significant first
+    pub(crate) limit: Option<usize>,        // maximum number of output rows, None for no limit
+}
+
+impl UnionAllQueryData {
+    pub fn selects(&self) -> &[SelectQueryData] {
+        &self.selects
+    }
+
+    /// Sort keys of the ORDER BY clause, among the columns of the first SELECT, most significant
+    /// first.
+    pub fn order_by(&self) -> &[OrderingTerm] {
+        &self.order_by
+    }
+
+    /// Maximum number of output rows, or `None` for no limit.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
+
+/// One comma-separated item of the SELECT list.
+#[derive(Debug)]
+pub(crate) enum SelectItem {
+    AllColumns,            // SELECT * FROM apples
+    Column(String),        // SELECT name FROM apples
+    Literal(Value),        // SELECT 1 FROM apples, emitted once per row
+    Count(Option<String>), // SELECT COUNT(*) or COUNT(color) FROM apples, per group of rows
+    Function {
+        function: ScalarFunction,
+        name: String, // function name as written, e.g. 'typeof', 'TYPEOF'
+        argument: Box<SelectItem>,
+    }, // SELECT typeof(color) FROM apples
+    Arithmetic {
+        operator: ArithmeticOperator,
+        left: Box<SelectItem>,
+        right: Box<SelectItem>,
+    }, // SELECT price * 2 FROM apples
+    Cast {
+        argument: Box<SelectItem>,
+        type_name: String, // as written, e.g. 'VARCHAR(10)'
+    }, // SELECT CAST(price AS INTEGER) FROM apples
+}
+
+impl SelectItem {
+    /// Whether the item holds an aggregate, i.e. a COUNT.
+    pub(crate) fn is_aggregate(&self) -> bool {
+        match self {
+            SelectItem::Count(_) => true,
+            SelectItem::Function { argument, .. } | SelectItem::Cast { argument, .. } => {
+                argument.is_aggregate()
+            }
+            SelectItem::Arithmetic { left, right, .. } => {
+                left.is_aggregate() || right.is_aggregate()
+            }
+            SelectItem::AllColumns | SelectItem::Column(_) | SelectItem::Literal(_) => false,
+        }
+    }
+}
+
+/// A `HAVING COUNT(...) <predicate>` clause: a group of rows must satisfy the predicate on its
+/// count.
+#[derive(Debug)]
+pub(crate) struct Having {
+    pub(crate) count_column: Option<String>, // None for COUNT(*)
+    pub(crate) predicate: Predicate,
+}
+
+/// A `WHERE <condition> AND <condition> ...` clause: a row must satisfy every condition.
+#[derive(Debug)]
+pub(crate) struct WhereClause {
+    pub(crate) conditions: Vec<Condition>,
+}
+
+/// A `<column> <predicate>` condition of a WHERE clause.
+#[derive(Debug)]
+pub(crate) struct Condition {
+    pub(crate) column_name: String,
+    pub(crate) predicate: Predicate,
+}
+
+#[derive(Debug)]
+pub(crate) enum Predicate {
+    Eq(Value),                        // WHERE color = 'Red'
+    In(Vec<Value>),                   // WHERE color IN ('Red', 'Yellow')
+    InSubquery(Box<SelectQueryData>), // WHERE id IN (SELECT apple_id FROM orders)
+    Like {
+        pattern: Value,       // WHERE name LIKE 'Gr%'
+        escape: Option<char>, // WHERE name LIKE '100\%' ESCAPE '\'
+    },
+    Glob(Value),                       // WHERE name GLOB 'Gr*'
+    Compare(Comparison, Value),        // WHERE id >= 10
+    CompareColumn(Comparison, String), // WHERE start <= end, with another column of the row
+}
+
+/// A comparison operator. `= <literal>` has its own predicate, see `Predicate::Eq`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Eq, // =
+    Lt, // <
+    Le, // <=
+    Gt, // >
+    Ge, // >=
+    Ne, // != or <>
+}
+
+impl Comparison {
+    /// Whether the comparison holds for a left operand ordered this way relative to the right one.
+    pub(crate) fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Eq => ordering.is_eq(),
+            Comparison::Lt => ordering.is_lt(),
+            Comparison::Le => ordering.is_le(),
+            Comparison::Gt => ordering.is_gt(),
+            Comparison::Ge => ordering.is_ge(),
+            Comparison::Ne => ordering.is_ne(),
+        }
+    }
+}
+
+/// A `CREATE TABLE` statement, as stored in the 'sql' column of the schema table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub table_name: String,
+    pub columns: Vec<ColumnDef>,
+}
+
+/// A column definition of a `CREATE TABLE` statement, along with its constraints.
+///
+/// Constraints are only captured for introspection: the crate is read-only, nothing enforces them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub type_name: Option<String>, // declared type as written, e.g. 'VARCHAR(255)'
+    pub not_null: bool,
+    pub primary_key: bool, // part of the primary key, declared on the column or on the table
+    pub default: Option<String>, // default value as written, e.g. '0', 'CURRENT_TIMESTAMP'
+}
+
+/// A `CREATE VIEW` statement: the view's query, and optionally names for its columns.
+#[derive(Debug)]
+pub(crate) struct CreateView {
+    pub(crate) col_names: Option<Vec<String>>,
+    pub(crate) select_query: SelectQueryData,
+}
+
+/// Parse a SQL query. Supported queries:
+/// - `SELECT COUNT(*) FROM <table>`, where the table name may be prefixed by the 'main' schema
+/// - `SELECT COUNT(<column>) FROM <table>`
+/// - `SELECT <item>, ... FROM <table> [WHERE <column> <predicate> [AND ...]]
+///   [GROUP BY <column>, ... [HAVING COUNT(...) <predicate>]] [ORDER BY <column> [ASC | DESC], ...]
+///   [LIMIT <integer>]`, where items are `*`, column names, literals, `COUNT(*)`, `COUNT(<column>)`
+///   or scalar function calls like `typeof(<item>)`, and predicates are
+///   `= <literal>`, `IN (<literal>, ...)`, `IN (SELECT <column> FROM <table> ...)` or
+///   `LIKE <literal> [ESCAPE <literal>]`, `GLOB <literal>` or a comparison like `>= <literal>`.
+///   In WHERE, a comparison may be with another column of the row instead, e.g. `start <= end`.
+///   A negative LIMIT means no limit.
+/// - `SELECT ... UNION ALL SELECT ... [ORDER BY <column> [ASC | DESC], ...] [LIMIT <integer>]`,
+///   where the ORDER BY and LIMIT clauses apply to the rows of all the SELECTs
+///
+/// Keywords are case-insensitive. A trailing ';' is allowed.
+pub fn parse_sql_query(sql_query: &str) -> Result<SQLQuery, SQLQueryParsingError> {
+    parse_sql_query_with_parameters(sql_query, &[])
+}
+
+/// Parse a SQL query holding `?` placeholders wherever a literal is expected, e.g. `SELECT name
+/// FROM apples WHERE color = ?`. Placeholders are bound to the given parameters, in order: there
+/// must be exactly one parameter per placeholder.
+///
+/// Unlike values pasted into the query text, parameters need no quoting or escaping.
+pub fn parse_sql_query_with_parameters(
+    sql_query: &str,
+    parameters: &[Value],
+) -> Result<SQLQuery, SQLQueryParsingError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql_query)?,
+        idx: 0,
+        parameters: parameters.to_vec(),
+        nb_placeholders: 0,
+    };
+    let query = parser.parse_query()?;
+    parser.next_if_kind(&TokenKind::Semicolon);
+    parser.expect_end()?;
+    if parser.nb_placeholders != parameters.len() {
+        return Err(SQLQueryParsingError::WrongParameterCount {
+            nb_placeholders: parser.nb_placeholders,
+            nb_parameters: parameters.len(),
+        });
+    }
+    Ok(query)
+}
+
+/// Parse a `CREATE TABLE` statement: its table name and column definitions.
+///
+/// Table constraints are parsed too, but only PRIMARY KEY ones are captured, on the columns they
+/// cover. Other constraints and clauses, e.g. CHECK expressions or foreign keys, are skipped.
+pub fn parse_create_table(sql: &str) -> Result<CreateTable, SQLQueryParsingError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        idx: 0,
+        parameters: Vec::new(),
+        nb_placeholders: 0,
+    };
+    let create_table = parser.parse_create_table(sql)?;
+    parser.next_if_kind(&TokenKind::Semicolon);
+    parser.expect_end()?;
+    Ok(create_table)
+}
+
+/// Parse the DEFAULT value of a column definition, as written, e.g. '-3' or '(\'none\')'. Only
+/// literals are supported: returns `None` for other expressions, e.g. 'CURRENT_TIMESTAMP'.
+pub(crate) fn parse_default_value(default: &str) -> Option<Value> {
+    let mut parser = Parser {
+        tokens: tokenize(default).ok()?,
+        idx: 0,
+        parameters: Vec::new(),
+        nb_placeholders: 0,
+    };
+    let is_parenthesized = parser.next_if_kind(&TokenKind::LeftParen);
+    let value = parser.parse_literal().ok()?;
+    if is_parenthesized {
+        parser.expect_kind(TokenKind::RightParen, ")").ok()?;
+    }
+    parser.expect_end().ok()?;
+    Some(value)
+}
+
+/// Parse the indexed column names of a `CREATE [UNIQUE] INDEX [IF NOT EXISTS] <name> ON <table>
+/// (<column> [COLLATE <collation>] [ASC | DESC], ...) [WHERE ...]` statement, in key order.
+///
+/// Used instead of a regular expression when the 'regex' feature is off.
+#[cfg(not(feature = "regex"))]
+pub(crate) fn parse_create_index(sql: &str) -> Result<Vec<String>, SQLQueryParsingError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        idx: 0,
+        parameters: Vec::new(),
+        nb_placeholders: 0,
+    };
+
+    parser.expect_keyword("CREATE")?;
+    parser.next_if_keyword("UNIQUE");
+    parser.expect_keyword("INDEX")?;
+    if parser.next_if_keyword("IF") {
+        parser.expect_keyword("NOT")?;
+        parser.expect_keyword("EXISTS")?;
+    }
+    parser.parse_table_name()?;
+    parser.expect_keyword("ON")?;
+    parser.parse_table_name()?;
+
+    parser.expect_kind(TokenKind::LeftParen, "(")?;
+    let mut col_names = Vec::new();
+    loop {
+        col_names.push(parser.parse_identifier("a column name")?);
+        // Skip the collation and sort order
+        parser.skip_until(|_| false)?;
+        if !parser.next_if_kind(&TokenKind::Comma) {
+            break;
+        }
+    }
+    parser.expect_kind(TokenKind::RightParen, ")")?;
+    Ok(col_names)
+}
+
+/// Parse a `CREATE VIEW <name> [(<column>, ...)] AS SELECT ...` statement. Only the queries that
+/// `parse_sql_query` supports as `SELECT <item>, ... FROM <table> ...` are supported.
+pub(crate) fn parse_create_view(sql: &str) -> Result<CreateView, SQLQueryParsingError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        idx: 0,
+        parameters: Vec::new(),
+        nb_placeholders: 0,
+    };
+
+    parser.expect_keyword("CREATE")?;
+    let _ = parser.next_if_keyword("TEMP") || parser.next_if_keyword("TEMPORARY");
+    parser.expect_keyword("VIEW")?;
+    if parser.next_if_keyword("IF") {
+        parser.expect_keyword("NOT")?;
+        parser.expect_keyword("EXISTS")?;
+    }
+    parser.parse_table_name()?;
+
+    let col_names = if parser.next_if_kind(&TokenKind::LeftParen) {
+        let mut col_names = vec![parser.parse_identifier("a column name")?];
+        while parser.next_if_kind(&TokenKind::Comma) {
+            col_names.push(parser.parse_identifier("a column name")?);
+        }
+        parser.expect_kind(TokenKind::RightParen, ")")?;
+        Some(col_names)
+    } else {
+        None
+    };
+
+    parser.expect_keyword("AS")?;
+    parser.expect_keyword("SELECT")?;
+    let select_query = parser.parse_select_body()?;
+    parser.next_if_kind(&TokenKind::Semicolon);
+    parser.expect_end()?;
+
+    Ok(CreateView {
+        col_names,
+        select_query,
+    })
+}
+
+/// Keywords starting a column constraint, which end the type name of a column definition.
+const COLUMN_CONSTRAINT_KEYWORDS: [&str; 11] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+/// Keywords starting a table constraint, which come after the column definitions.
+const TABLE_CONSTRAINT_KEYWORDS: [&str; 5] =
+    ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+/// Recursive descent parser over the query tokens.
+struct Parser {
+    tokens: Vec<Token>,
+    idx: usize,             // index of the next token
+    parameters: Vec<Value>, // values bound to the `?` placeholders, in order
+    nb_placeholders: usize, // placeholders parsed so far
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<Token, SQLQueryParsingError> {
+        let token = self
+            .tokens
+            .get(self.idx)
+            .cloned()
+            .ok_or(SQLQueryParsingError::UnexpectedEnd(expected))?;
+        self.idx += 1;
+        Ok(token)
+    }
+
+    fn next_if_kind(&mut self, kind: &TokenKind) -> bool {
+        let is_match = self.peek().is_some_and(|token| token.kind == *kind);
+        if is_match {
+            self.idx += 1;
+        }
+        is_match
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token { kind: TokenKind::Identifier(identifier), .. })
+                if identifier.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn next_if_keyword(&mut self, keyword: &str) -> bool {
+        let is_match = self.peek_keyword(keyword);
+        if is_match {
+            self.idx += 1;
+        }
+        is_match
+    }
+
+    fn next_if_comparison(&mut self) -> Option<Comparison> {
+        let comparison = match &self.peek()?.kind {
+            TokenKind::Operator(operator) => match operator.as_str() {
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                ">=" => Comparison::Ge,
+                "!=" | "<>" => Comparison::Ne,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.idx += 1;
+        Some(comparison)
+    }
+
+    fn unexpected(token: Token, expected: &'static str) -> SQLQueryParsingError {
+        SQLQueryParsingError::UnexpectedToken {
+            expected,
+            found: token.kind.to_string(),
+            position: token.position,
+        }
+    }
+
+    fn expect_kind(
+        &mut self,
+        kind: TokenKind,
+        expected: &'static str,
+    ) -> Result<(), SQLQueryParsingError> {
+        let token = self.next(expected)?;
+        if token.kind == kind {
+            Ok(())
+        } else {
+            Err(Self::unexpected(token, expected))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &'static str) -> Result<(), SQLQueryParsingError> {
+        let token = self.next(keyword)?;
+        match &token.kind {
+            TokenKind::Identifier(identifier) if identifier.eq_ignore_ascii_case(keyword) => Ok(()),
+            _ => Err(Self::unexpected(token, keyword)),
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), SQLQueryParsingError> {
+        match self.peek() {
+            Some(token) => Err(Self::unexpected(token.clone(), "the end of the query")),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_identifier(&mut self, expected: &'static str) -> Result<String, SQLQueryParsingError> {
+        let token = self.next(expected)?;
+        match token.kind {
+            TokenKind::Identifier(identifier) | TokenKind::QuotedIdentifier(identifier) => {
+                Ok(identifier)
+            }
+            _ => Err(Self::unexpected(token, expected)),
+        }
+    }
+
+    /// Parse a table name, optionally qualified by its schema: `apples` or `main.apples`.
+    ///
+    /// Only the 'main' schema, i.e. the database file itself, can be read: other schemas like
+    /// 'temp' or attached databases are rejected.
+    fn parse_table_name(&mut self) -> Result<String, SQLQueryParsingError> {
+        let schema_position = self.peek().map(|token| token.position);
+        let name = self.parse_identifier("a table name")?;
+        if !self.next_if_kind(&TokenKind::Dot) {
+            return Ok(name);
+        }
+
+        if !name.eq_ignore_ascii_case("main") {
+            return Err(SQLQueryParsingError::UnknownSchema {
+                schema: name,
+                position: schema_position.expect("the schema name was just parsed"),
+            });
+        }
+        self.parse_identifier("a table name")
+    }
+
+    /// The SQL text from the given position up to the next token, without trailing whitespace.
+    fn text_since(&self, sql: &str, start: usize) -> String {
+        let end = self.peek().map_or(sql.len(), |token| token.position);
+        sql[start..end].trim_end().to_string()
+    }
+
+    /// Skip a parenthesized group of tokens, nested parentheses included.
+    fn skip_parenthesized(&mut self) -> Result<(), SQLQueryParsingError> {
+        self.expect_kind(TokenKind::LeftParen, "(")?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next(")")?.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip tokens up to the ',' or ')' ending the current column definition or table constraint,
+    /// or up to a token for which `stop` returns true.
+    fn skip_until(&mut self, stop: impl Fn(&Self) -> bool) -> Result<(), SQLQueryParsingError> {
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                None | Some(TokenKind::Comma | TokenKind::RightParen) => return Ok(()),
+                Some(TokenKind::LeftParen) => self.skip_parenthesized()?,
+                Some(_) if stop(self) => return Ok(()),
+                Some(_) => self.idx += 1,
+            }
+        }
+    }
+
+    /// Skip an optional `ON CONFLICT <resolution>` clause.
+    fn skip_conflict_clause(&mut self) -> Result<(), SQLQueryParsingError> {
+        if self.next_if_keyword("ON") {
+            self.expect_keyword("CONFLICT")?;
+            self.parse_identifier("a conflict resolution")?;
+        }
+        Ok(())
+    }
+
+    fn parse_create_table(&mut self, sql: &str) -> Result<CreateTable, SQLQueryParsingError> {
+        self.expect_keyword("CREATE")?;
+        let _ = self.next_if_keyword("TEMP") || self.next_if_keyword("TEMPORARY");
+        self.expect_keyword("TABLE")?;
+        if self.next_if_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+        }
+        let table_name = self.parse_table_name()?;
+
+        self.expect_kind(TokenKind::LeftParen, "(")?;
+        let mut columns = Vec::new();
+        loop {
+            if TABLE_CONSTRAINT_KEYWORDS
+                .iter()
+                .any(|keyword| self.peek_keyword(keyword))
+            {
+                self.parse_table_constraint(&mut columns)?;
+            } else {
+                columns.push(self.parse_column_def(sql)?);
+            }
+            if !self.next_if_kind(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_kind(TokenKind::RightParen, ")")?;
+
+        // Table options: WITHOUT ROWID, STRICT
+        loop {
+            if self.next_if_keyword("WITHOUT") {
+                self.expect_keyword("ROWID")?;
+            } else if !self.next_if_keyword("STRICT") {
+                break;
+            }
+            if !self.next_if_kind(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        Ok(CreateTable {
+            table_name,
+            columns,
+        })
+    }
+
+    /// Parse a column definition: `<name> [<type>] [<constraint> ...]`.
+    fn parse_column_def(&mut self, sql: &str) -> Result<ColumnDef, SQLQueryParsingError> {
+        let name = self.parse_identifier("a column name")?;
+
+        // The type name is made of any number of names, e.g. 'UNSIGNED BIG INT', optionally
+        // followed by sizes, e.g. 'DECIMAL(10, 5)'
+        let type_start = self.peek().map(|token| token.position);
+        let mut has_type = false;
+        while matches!(
+            self.peek().map(|token| &token.kind),
+            Some(TokenKind::Identifier(_) | TokenKind::QuotedIdentifier(_) | TokenKind::String(_))
+        ) && !COLUMN_CONSTRAINT_KEYWORDS
+            .iter()
+            .any(|keyword| self.peek_keyword(keyword))
+        {
+            self.idx += 1;
+            has_type = true;
+        }
+        if has_type
+            && self
+                .peek()
+                .is_some_and(|token| token.kind == TokenKind::LeftParen)
+        {
+            self.skip_parenthesized()?;
+        }
+        let type_name = type_start
+            .filter(|_| has_type)
+            .map(|start| self.text_since(sql, start));
+
+        let mut column_def = ColumnDef {
+            name,
+            type_name,
+            not_null: false,
+            primary_key: false,
+            default: None,
+        };
+        loop {
+            if self.next_if_keyword("CONSTRAINT") {
+                self.parse_identifier("a constraint name")?;
+            } else if self.next_if_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                let _ = self.next_if_keyword("ASC") || self.next_if_keyword("DESC");
+                self.skip_conflict_clause()?;
+                self.next_if_keyword("AUTOINCREMENT");
+                column_def.primary_key = true;
+            } else if self.next_if_keyword("NOT") {
+                self.expect_keyword("NULL")?;
+                self.skip_conflict_clause()?;
+                column_def.not_null = true;
+            } else if self.next_if_keyword("NULL") {
+            } else if self.next_if_keyword("UNIQUE") {
+                self.skip_conflict_clause()?;
+            } else if self.next_if_keyword("CHECK") {
+                self.skip_parenthesized()?;
+            } else if self.next_if_keyword("DEFAULT") {
+                let default_start = self
+                    .peek()
+                    .map(|token| token.position)
+                    .ok_or(SQLQueryParsingError::UnexpectedEnd("a default value"))?;
+                if self
+                    .peek()
+                    .is_some_and(|token| token.kind == TokenKind::LeftParen)
+                {
+                    self.skip_parenthesized()?;
+                } else {
+                    // A signed number, a literal or an identifier like CURRENT_TIMESTAMP
+                    let sign = TokenKind::Operator("+".to_string());
+                    let _ = self.next_if_kind(&TokenKind::Minus) || self.next_if_kind(&sign);
+                    self.next("a default value")?;
+                }
+                column_def.default = Some(self.text_since(sql, default_start));
+            } else if self.next_if_keyword("COLLATE") {
+                self.parse_identifier("a collation name")?;
+            } else if self.next_if_keyword("REFERENCES") {
+                // The foreign key clause goes on until the next constraint. A NOT may also start
+                // a 'NOT DEFERRABLE' clause, a NULL may be part of 'ON DELETE SET NULL'.
+                self.parse_identifier("a table name")?;
+                if self
+                    .peek()
+                    .is_some_and(|token| token.kind == TokenKind::LeftParen)
+                {
+                    self.skip_parenthesized()?;
+                }
+                self.skip_until(|parser| {
+                    COLUMN_CONSTRAINT_KEYWORDS
+                        .iter()
+                        .filter(|&&keyword| keyword != "NOT" && keyword != "NULL")
+                        .any(|keyword| parser.peek_keyword(keyword))
+                        || parser.peek_keyword("NOT")
+                            && matches!(
+                                parser.tokens.get(parser.idx + 1),
+                                Some(Token { kind: TokenKind::Identifier(identifier), .. })
+                                    if identifier.eq_ignore_ascii_case("NULL")
+                            )
+                })?;
+            } else if self.next_if_keyword("GENERATED") {
+                self.expect_keyword("ALWAYS")?;
+            } else if self.next_if_keyword("AS") {
+                self.skip_parenthesized()?;
+                let _ = self.next_if_keyword("STORED") || self.next_if_keyword("VIRTUAL");
+            } else {
+                break;
+            }
+        }
+
+        Ok(column_def)
+    }
+
+    /// Parse a table constraint, marking the columns of a PRIMARY KEY constraint.
+    fn parse_table_constraint(
+        &mut self,
+        columns: &mut [ColumnDef],
+    ) -> Result<(), SQLQueryParsingError> {
+        if self.next_if_keyword("CONSTRAINT") {
+            self.parse_identifier("a constraint name")?;
+        }
+        if self.next_if_keyword("PRIMARY") {
+            self.expect_keyword("KEY")?;
+            self.expect_kind(TokenKind::LeftParen, "(")?;
+            loop {
+                let column_name = self.parse_identifier("a column name")?;
+                if let Some(column_def) = columns
+                    .iter_mut()
+                    .find(|column_def| column_def.name.eq_ignore_ascii_case(&column_name))
+                {
+                    column_def.primary_key = true;
+                }
+                // Skip the COLLATE and ASC/DESC of the indexed column
+                self.skip_until(|_| false)?;
+                if !self.next_if_kind(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect_kind(TokenKind::RightParen, ")")?;
+        }
+        // UNIQUE (...), CHECK (...) and FOREIGN KEY (...) REFERENCES ... are skipped
+        self.skip_until(|_| false)
+    }
+
+    fn parse_query(&mut self) -> Result<SQLQuery, SQLQueryParsingError> {
+        self.expect_keyword("SELECT")?;
+        let select_query = self.parse_select_body()?;
+
+        // Only the last SELECT of a compound query may have ORDER BY and LIMIT clauses, which
+        // apply to the whole output
+        let ends_compound = |select_query: &SelectQueryData| {
+            !select_query.order_by.is_empty() || select_query.limit.is_some()
+        };
+        if !ends_compound(&select_query) && self.next_if_keyword("UNION") {
+            let mut selects = vec![select_query];
+            loop {
+                self.expect_keyword("ALL")?;
+                self.expect_keyword("SELECT")?;
+                let select_query = self.parse_select_body()?;
+                let is_last = ends_compound(&select_query) || !self.next_if_keyword("UNION");
+                selects.push(select_query);
+                if is_last {
+                    break;
+                }
+            }
+            let (order_by, limit) = selects
+                .last_mut()
+                .map(|select_query| {
+                    (
+                        std::mem::take(&mut select_query.order_by),
+                        select_query.limit.take(),
+                    )
+                })
+                .unwrap_or_default();
+            return Ok(SQLQuery::UnionAll(UnionAllQueryData {
+                selects,
+                order_by,
+                limit,
+            }));
+        }
+
+        // A lone COUNT over a whole table has its own queries, which avoid decoding every row
+        if let SelectQueryData {
+            select_items,
+            where_clause: None,
+            group_by,
+            having: None,
+            order_by,
+            limit: None,
+            ..
+        } = &select_query
+        {
+            if let ([SelectItem::Count(column_name)], [], []) = (
+                select_items.as_slice(),
+                group_by.as_slice(),
+                order_by.as_slice(),
+            ) {
+                let table_name = select_query.table_name.clone();
+                return Ok(match column_name {
+                    None => SQLQuery::CountRows(table_name),
+                    Some(column_name) => SQLQuery::CountNonNull(CountNonNullQueryData {
+                        table_name,
+                        column_name: column_name.clone(),
+                    }),
+                });
+            }
+        }
+
+        Ok(SQLQuery::Select(select_query))
+    }
+
+    /// Parse what follows the SELECT keyword of a
+    /// `SELECT <items> FROM <table> [WHERE ...] [GROUP BY ... [HAVING ...]] [ORDER BY ...]` query.
+    fn parse_select_body(&mut self) -> Result<SelectQueryData, SQLQueryParsingError> {
+        // 'SELECT FROM t' would otherwise read FROM as a column name, and fail on the table name
+        if self.peek_keyword("FROM") {
+            let position = self.peek().map(|token| token.position).unwrap_or_default();
+            return Err(SQLQueryParsingError::EmptySelectList { position });
+        }
+        let mut select_items = vec![self.parse_select_item()?];
+        while self.next_if_kind(&TokenKind::Comma) {
+            select_items.push(self.parse_select_item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let table_name = self.parse_table_name()?;
+
+        let where_clause = if self.next_if_keyword("WHERE") {
+            Some(self.parse_where_clause()?)
+        } else {
+            None
+        };
+
+        let mut group_by = Vec::new();
+        if self.next_if_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_identifier("a column name")?);
+            while self.next_if_kind(&TokenKind::Comma) {
+                group_by.push(self.parse_identifier("a column name")?);
+            }
+        }
+
+        // Without GROUP BY, HAVING filters the single group of all the rows
+        let having = if self.next_if_keyword("HAVING") {
+            let count_column = self.parse_count()?;
+            let predicate = self.parse_predicate()?;
+            Some(Having {
+                count_column,
+                predicate,
+            })
+        } else {
+            None
+        };
+
+        let mut order_by = Vec::new();
+        if self.next_if_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.parse_ordering_term()?);
+            while self.next_if_kind(&TokenKind::Comma) {
+                order_by.push(self.parse_ordering_term()?);
+            }
+        }
+
+        let limit = if self.next_if_keyword("LIMIT") {
+            self.parse_limit()?
+        } else {
+            None
+        };
+
+        Ok(SelectQueryData {
+            table_name,
+            select_items,
+            where_clause,
+            group_by,
+            having,
+            order_by,
+            limit,
+        })
+    }
+
+    /// Parse a sort key of an ORDER BY clause: `<column> [ASC | DESC]`.
+    ///
+    /// Like in SQLite, a column may be named 'asc' or 'desc': the first token is always the
+    /// column, a direction can only follow it.
+    fn parse_ordering_term(&mut self) -> Result<OrderingTerm, SQLQueryParsingError> {
+        let column_name = self.parse_identifier("a column name")?;
+        let descending = if self.next_if_keyword("DESC") {
+            true
+        } else {
+            self.next_if_keyword("ASC");
+            false
+        };
+        Ok(OrderingTerm {
+            column_name,
+            descending,
+        })
+    }
+
+    /// Parse the row count following LIMIT. Like SQLite, a negative count means no limit, and the
+    /// count may be given as text or as a real, as long as it holds an integer, e.g. '10' or 10.0.
+    fn parse_limit(&mut self) -> Result<Option<usize>, SQLQueryParsingError> {
+        let as_integer = |x: f64| (x.fract() == 0.0 && x.abs() < 9.2e18).then_some(x as i64);
+        let nb_rows = match self.parse_literal()? {
+            Value::Integer(n) => Some(n),
+            Value::Real(x) => as_integer(x),
+            Value::Text(text) => {
+                let text = text.trim();
+                text.parse::<i64>()
+                    .ok()
+                    .or_else(|| text.parse::<f64>().ok().and_then(as_integer))
+            }
+            Value::Null | Value::Blob(_) => None,
+        };
+        match nb_rows {
+            Some(nb_rows) => Ok(usize::try_from(nb_rows).ok()),
+            None => Err(SQLQueryParsingError::NonIntegerLimit),
+        }
+    }
+
+    /// Parse one SELECT item: `*`, a literal (emitted as-is for each row), `COUNT(...)`, a scalar
+    /// function call or a column name.
+    fn parse_select_item(&mut self) -> Result<SelectItem, SQLQueryParsingError> {
+        if self.next_if_kind(&TokenKind::Star) {
+            return Ok(SelectItem::AllColumns);
+        }
+        self.parse_value_item()
+    }
+
+    /// Parse a SELECT item standing for a single value: anything but `*`. Operands may be combined
+    /// with the `+ - * /` operators, `*` and `/` binding tighter, and grouped with parentheses.
+    fn parse_value_item(&mut self) -> Result<SelectItem, SQLQueryParsingError> {
+        let mut item = self.parse_product()?;
+        while let Some(operator) = self
+            .next_if_arithmetic_operator(&[ArithmeticOperator::Add, ArithmeticOperator::Subtract])
+        {
+            item = SelectItem::Arithmetic {
+                operator,
+                left: Box::new(item),
+                right: Box::new(self.parse_product()?),
+            };
+        }
+        Ok(item)
+    }
+
+    /// Parse operands joined by `*` and `/`.
+    fn parse_product(&mut self) -> Result<SelectItem, SQLQueryParsingError> {
+        let mut item = self.parse_operand()?;
+        while let Some(operator) = self.next_if_arithmetic_operator(&[
+            ArithmeticOperator::Multiply,
+            ArithmeticOperator::Divide,
+        ]) {
+            item = SelectItem::Arithmetic {
+                operator,
+                left: Box::new(item),
+                right: Box::new(self.parse_operand()?),
+            };
+        }
+        Ok(item)
+    }
+
+    /// Consume the next token if it is one of the given arithmetic operators.
+    fn next_if_arithmetic_operator(
+        &mut self,
+        operators: &[ArithmeticOperator],
+    ) -> Option<ArithmeticOperator> {
+        let operator = match &self.peek()?.kind {
+            TokenKind::Operator(operator) if operator == "+" => ArithmeticOperator::Add,
+            TokenKind::Minus => ArithmeticOperator::Subtract,
+            TokenKind::Star => ArithmeticOperator::Multiply,
+            TokenKind::Operator(operator) if operator == "/" => ArithmeticOperator::Divide,
+            _ => return None,
+        };
+        if !operators.contains(&operator) {
+            return None;
+        }
+        self.idx += 1;
+        Some(operator)
+    }
+
+    /// Parse an operand of a SELECT item: a parenthesized item, `COUNT(...)`, a scalar function
+    /// call, a column name or a literal.
+    fn parse_operand(&mut self) -> Result<SelectItem, SQLQueryParsingError> {
+        if self.next_if_kind(&TokenKind::LeftParen) {
+            let item = self.parse_value_item()?;
+            self.expect_kind(TokenKind::RightParen, ")")?;
+            return Ok(item);
+        }
+        // A name is a function name when followed by '(': 'count' alone is a column name
+        let is_call = matches!(
+            self.tokens.get(self.idx + 1),
+            Some(Token {
+                kind: TokenKind::LeftParen,
+                ..
+            })
+        );
+        if is_call && self.peek_keyword("COUNT") {
+            return Ok(SelectItem::Count(self.parse_count()?));
+        }
+        if is_call && self.peek_keyword("CAST") {
+            return self.parse_cast();
+        }
+        if let (true, Some(TokenKind::Identifier(name))) =
+            (is_call, self.peek().map(|token| &token.kind))
+        {
+            let name = name.clone();
+            let function = ScalarFunction::from_name(&name)
+                .ok_or_else(|| SQLQueryParsingError::UnknownFunction(name.clone()))?;
+            self.idx += 1;
+            self.expect_kind(TokenKind::LeftParen, "(")?;
+            let argument = self.parse_value_item()?;
+            self.expect_kind(TokenKind::RightParen, ")")?;
+            return Ok(SelectItem::Function {
+                function,
+                name,
+                argument: Box::new(argument),
+            });
+        }
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Identifier(identifier)) if !identifier.eq_ignore_ascii_case("null") => {
+                Ok(SelectItem::Column(self.parse_identifier("a column name")?))
+            }
+            Some(TokenKind::QuotedIdentifier(_)) => {
+                Ok(SelectItem::Column(self.parse_identifier("a column name")?))
+            }
+            _ => Ok(SelectItem::Literal(self.parse_literal()?)),
+        }
+    }
+
+    /// Parse `CAST(<item> AS <type>)`. Like in column definitions, the type name is made of any
+    /// number of names, optionally followed by sizes, e.g. 'DECIMAL(10, 5)'.
+    fn parse_cast(&mut self) -> Result<SelectItem, SQLQueryParsingError> {
+        self.expect_keyword("CAST")?;
+        self.expect_kind(TokenKind::LeftParen, "(")?;
+        let argument = self.parse_value_item()?;
+        self.expect_keyword("AS")?;
+        let mut type_name = self.parse_identifier("a type name")?;
+        while let Some(TokenKind::Identifier(name) | TokenKind::QuotedIdentifier(name)) =
+            self.peek().map(|token| &token.kind)
+        {
+            type_name = format!("{type_name} {name}");
+            self.idx += 1;
+        }
+        if self.next_if_kind(&TokenKind::LeftParen) {
+            let mut sizes = vec![self.parse_literal()?.to_string()];
+            while self.next_if_kind(&TokenKind::Comma) {
+                sizes.push(self.parse_literal()?.to_string());
+            }
+            self.expect_kind(TokenKind::RightParen, ")")?;
+            type_name = format!("{type_name}({})", sizes.join(","));
+        }
+        self.expect_kind(TokenKind::RightParen, ")")?;
+        Ok(SelectItem::Cast {
+            argument: Box::new(argument),
+            type_name,
+        })
+    }
+
+    /// Parse `COUNT(*)` or `COUNT(<column>)`, returning the column name, if any.
+    fn parse_count(&mut self) -> Result<Option<String>, SQLQueryParsingError> {
+        self.expect_keyword("COUNT")?;
+        self.expect_kind(TokenKind::LeftParen, "(")?;
+        let column_name = if self.next_if_kind(&TokenKind::Star) {
+            None
+        } else {
+            Some(self.parse_identifier("* or a column name")?)
+        };
+        self.expect_kind(TokenKind::RightParen, ")")?;
+        Ok(column_name)
+    }
+
+    /// Parse a WHERE clause: conditions joined by AND.
+    fn parse_where_clause(&mut self) -> Result<WhereClause, SQLQueryParsingError> {
+        let mut conditions = vec![self.parse_condition()?];
+        while self.next_if_keyword("AND") {
+            conditions.push(self.parse_condition()?);
+        }
+        Ok(WhereClause { conditions })
+    }
+
+    /// Parse a WHERE condition. Supported forms:
+    /// - `<column> = <literal>`
+    /// - `<column> IN (<literal>, ...)`
+    /// - `<column> IN (SELECT <column> FROM <table> ...)`
+    /// - `<column> LIKE <literal> [ESCAPE <literal>]`
+    /// - `<column> GLOB <literal>`
+    /// - `<column> <op> <literal>`, where op is one of '<', '<=', '>', '>=', '!=' and '<>'
+    fn parse_condition(&mut self) -> Result<Condition, SQLQueryParsingError> {
+        let column_name = self.parse_identifier("a column name")?;
+        let predicate = self.parse_predicate()?;
+        Ok(Condition {
+            column_name,
+            predicate,
+        })
+    }
+
+    /// Parse what follows the column of a WHERE condition, see `parse_condition`.
+    fn parse_predicate(&mut self) -> Result<Predicate, SQLQueryParsingError> {
+        let predicate = if self.next_if_keyword("IN") {
+            self.expect_kind(TokenKind::LeftParen, "(")?;
+            let predicate = if self.next_if_keyword("SELECT") {
+                Predicate::InSubquery(Box::new(self.parse_select_body()?))
+            } else {
+                let mut values = vec![self.parse_literal()?];
+                while self.next_if_kind(&TokenKind::Comma) {
+                    values.push(self.parse_literal()?);
+                }
+                Predicate::In(values)
+            };
+            self.expect_kind(TokenKind::RightParen, ")")?;
+            predicate
+        } else if self.next_if_keyword("LIKE") {
+            let pattern = self.parse_literal()?;
+            let escape = if self.next_if_keyword("ESCAPE") {
+                // The escape must be a single character
+                let escape = self.parse_literal()?;
+                match &escape {
+                    Value::Text(text) if text.chars().count() == 1 => text.chars().next(),
+                    _ => return Err(SQLQueryParsingError::InvalidEscape(escape.to_string())),
+                }
+            } else {
+                None
+            };
+            Predicate::Like { pattern, escape }
+        } else if self.next_if_keyword("GLOB") {
+            Predicate::Glob(self.parse_literal()?)
+        } else if let Some(comparison) = self.next_if_comparison() {
+            match self.next_if_column_name() {
+                Some(column_name) => Predicate::CompareColumn(comparison, column_name),
+                None => Predicate::Compare(comparison, self.parse_literal()?),
+            }
+        } else {
+            self.expect_kind(TokenKind::Equals, "a comparison operator, IN, LIKE or GLOB")?;
+            match self.next_if_column_name() {
+                Some(column_name) => Predicate::CompareColumn(Comparison::Eq, column_name),
+                None => Predicate::Eq(self.parse_literal()?),
+            }
+        };
+        Ok(predicate)
+    }
+
+    /// Parse the column name on the right-hand side of a comparison, e.g. 'end' in `start <= end`.
+    /// Returns `None` for a literal, "double-quoted" names included, see `parse_literal`.
+    fn next_if_column_name(&mut self) -> Option<String> {
+        match &self.peek()?.kind {
+            TokenKind::Identifier(name) if !name.eq_ignore_ascii_case("null") => {
+                let name = name.clone();
+                self.idx += 1;
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a literal: 'text', NULL or a number, optionally negated.
+    ///
+    /// Like SQLite, a "double-quoted" identifier where a literal is expected is read as text.
+    /// Both forms can escape their quote by doubling it: 'it''s', "say ""hi""".
+    fn parse_literal(&mut self) -> Result<Value, SQLQueryParsingError> {
+        let negated = self.next_if_kind(&TokenKind::Minus);
+        let token = self.next("a literal")?;
+        match (token.kind, negated) {
+            (TokenKind::String(text), false) => Ok(Value::Text(text)),
+            (TokenKind::QuotedIdentifier(text), false) => Ok(Value::Text(text)),
+            (TokenKind::Identifier(identifier), false)
+                if identifier.eq_ignore_ascii_case("null") =>
+            {
+                Ok(Value::Null)
+            }
+            // A missing parameter is reported once the whole query is parsed, see
+            // `parse_sql_query_with_parameters`
+            (TokenKind::Parameter, false) => {
+                let value = self.parameters.get(self.nb_placeholders).cloned();
+                self.nb_placeholders += 1;
+                Ok(value.unwrap_or(Value::Null))
+            }
+            (TokenKind::Number(number), negated) => match parse_number(&number)? {
+                Value::Integer(n) if negated => Ok(Value::Integer(n.wrapping_neg())),
+                Value::Real(x) if negated => Ok(Value::Real(-x)),
+                value => Ok(value),
+            },
+            (kind, _) => Err(Self::unexpected(
+                Token {
+                    kind,
+                    position: token.position,
+                },
+                "a literal",
+            )),
+        }
+    }
+}
+
+/// Parse a numeric literal into a `Value`.
+///
+/// Supported forms: decimal integers, hexadecimal integers (0x1F / 0X1F) and reals, possibly in
+/// scientific notation (1.5e3, 2E-2).
+/// Like SQLite, hexadecimal literals are read as 64-bit twos-complement integers, so
+/// '0xFFFFFFFFFFFFFFFF' is -1.
+fn parse_number(literal: &str) -> Result<Value, SQLQueryParsingError> {
+    let bad_literal = || SQLQueryParsingError::BadLiteral(literal.to_string());
+
+    if let Some(hex_digits) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        let n = u64::from_str_radix(hex_digits, 16).map_err(|_| bad_literal())?;
+        return Ok(Value::Integer(n as i64));
+    }
+    if let Ok(n) = literal.parse::<i64>() {
+        return Ok(Value::Integer(n));
+    }
+    literal
+        .parse::<f64>()
+        .map(Value::Real)
+        .map_err(|_| bad_literal())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_table_captures_column_constraints() {
+        let create_table = parse_create_table(
+            "CREATE TABLE accounts (
+                id INTEGER PRIMARY KEY,
+                owner VARCHAR(255) NOT NULL,
+                balance REAL DEFAULT 0.0 NOT NULL,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                note
+            )",
+        )
+        .unwrap();
+        assert_eq!(create_table.table_name, "accounts");
+
+        let column = |name: &str, type_name: Option<&str>, default: Option<&str>| ColumnDef {
+            name: name.to_string(),
+            type_name: type_name.map(str::to_string),
+            not_null: false,
+            primary_key: false,
+            default: default.map(str::to_string),
+        };
+        assert_eq!(
+            create_table.columns,
+            vec![
+                ColumnDef {
+                    primary_key: true,
+                    ..column("id", Some("INTEGER"), None)
+                },
+                ColumnDef {
+                    not_null: true,
+                    ..column("owner", Some("VARCHAR(255)"), None)
+                },
+                ColumnDef {
+                    not_null: true,
+                    ..column("balance", Some("REAL"), Some("0.0"))
+                },
+                column("created_at", Some("TEXT"), Some("CURRENT_TIMESTAMP")),
+                column("note", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn table_primary_key_constraint_marks_its_columns() {
+        let create_table =
+            parse_create_table("CREATE TABLE pairs (a INT, b INT, c INT, PRIMARY KEY (a, b))")
+                .unwrap();
+        let primary_key = create_table
+            .columns
+            .iter()
+            .map(|column| column.primary_key)
+            .collect::<Vec<_>>();
+        assert_eq!(primary_key, [true, true, false]);
+    }
+
+    #[test]
+    fn query_parses_from_a_string() {
+        let query = "SELECT name FROM apples ORDER BY name"
+            .parse::<SQLQuery>()
+            .unwrap();
+        match query {
+            SQLQuery::Select(select) => {
+                assert_eq!(select.table_name(), "apples");
+                assert_eq!(select.order_by()[0].column_name, "name");
+            }
+            query => panic!("expected a SELECT query, got {query:?}"),
+        }
+        assert!(matches!(
+            "SELECT COUNT(*) FROM apples".parse(),
+            Ok(SQLQuery::CountRows(table_name)) if table_name == "apples"
+        ));
+        assert!("SELECT".parse::<SQLQuery>().is_err());
+    }
+
+    #[test]
+    fn scientific_notation_literals_are_reals() {
+        assert_eq!(parse_number("1.5e3").unwrap(), Value::Real(1500.0));
+        assert_eq!(parse_number("2E-2").unwrap(), Value::Real(0.02));
+        assert_eq!(parse_number("1e+2").unwrap(), Value::Real(100.0));
+        assert!(matches!(
+            parse_number("1e"),
+            Err(SQLQueryParsingError::BadLiteral(literal)) if literal == "1e"
+        ));
+    }
+
+    /// The ORDER BY terms of a SELECT query.
+    fn order_by(sql: &str) -> Vec<(String, bool)> {
+        match parse_sql_query(sql).unwrap() {
+            SQLQuery::Select(select) => select
+                .order_by()
+                .iter()
+                .map(|term| (term.column_name.clone(), term.descending))
+                .collect(),
+            query => panic!("expected a SELECT query, got {query:?}"),
+        }
+    }
+
+    #[test]
+    fn order_by_direction_in_any_case() {
+        assert_eq!(
+            order_by("SELECT name FROM apples ORDER BY name desc, id Asc"),
+            [("name".to_string(), true), ("id".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn order_by_a_column_named_desc() {
+        // The first token of a sort key is the column
+        assert_eq!(
+            order_by("SELECT name FROM notes ORDER BY desc"),
+            [("desc".to_string(), false)]
+        );
+        assert_eq!(
+            order_by("SELECT name FROM notes ORDER BY desc DESC"),
+            [("desc".to_string(), true)]
+        );
+        // A direction only comes last
+        assert!(parse_sql_query("SELECT name FROM notes ORDER BY name DESC ASC").is_err());
+    }
+
+    #[test]
+    fn select_without_columns_is_an_empty_select_list() {
+        for (sql, expected_position) in [
+            ("SELECT FROM apples", 7),
+            ("select   from apples WHERE id = 1", 9),
+            ("SELECT\n\tFROM apples", 8),
+        ] {
+            match parse_sql_query(sql) {
+                Err(SQLQueryParsingError::EmptySelectList { position }) => {
+                    assert_eq!(position, expected_position, "{sql:?}")
+                }
+                result => panic!("{sql:?} should have no select list, got {result:?}"),
+            }
+        }
+        assert_eq!(
+            parse_sql_query("SELECT FROM apples")
+                .unwrap_err()
+                .to_string(),
+            "missing columns at position 7: expected a column, an expression or '*' between \
+             SELECT and FROM"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+#[cfg(test)]
+use std::cell::Cell;
+
+use crate::varint::decode_varint;
+use crate::{SQLiteInternalError, SerialTypeError, TextEncoding, Value};
+
+#[cfg(test)]
+thread_local! {
+    /// Number of values decoded by the current thread, for tests checking what a query decodes.
+    pub(crate) static DECODED_VALUES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Parse a record[1] from the bytes of a cell payload.
+///
+/// Record structure:
+/// - header size (varint), counting the bytes of this varint too
+/// - one serial type (varint) per column
+/// - the body: the column values, back to back
+///
+/// Text values are decoded with the text encoding of the database.
+///
+/// [1]: https://www.sqlite.org/fileformat2.html#record_format
+pub(crate) fn parse_record(
+    bytes: &[u8],
+    encoding: TextEncoding,
+) -> Result<Vec<Value>, SQLiteInternalError> {
+    decode_record(bytes, encoding, |_| true)
+}
+
+/// Parse a record, only decoding the columns flagged in `decoded_cols`: the other ones are read as
+/// NULL, which saves decoding the text and blobs a query does not look at. The record keeps all
+/// its columns.
+pub(crate) fn parse_record_columns(
+    bytes: &[u8],
+    decoded_cols: &[bool],
+    encoding: TextEncoding,
+) -> Result<Vec<Value>, SQLiteInternalError> {
+    decode_record(bytes, encoding, |col_idx| {
+        decoded_cols.get(col_idx).copied().unwrap_or(false)
+    })
+}
+
+fn decode_record(
+    bytes: &[u8],
+    encoding: TextEncoding,
+    is_decoded: impl Fn(usize) -> bool,
+) -> Result<Vec<Value>, SQLiteInternalError> {
+    let (columns_serial_types, header_size) = parse_record_header(bytes)?;
+
+    // Reading the record body
+    let mut body_offset = header_size;
+    let mut values = Vec::with_capacity(columns_serial_types.len());
+    for (col_idx, serial_type) in columns_serial_types.into_iter().enumerate() {
+        let col_bytes = column_bytes(bytes, body_offset, serial_type)?;
+        values.push(if is_decoded(col_idx) {
+            decode_value(serial_type, col_bytes, encoding)?
+        } else {
+            Value::Null
+        });
+        body_offset += col_bytes.len();
+    }
+
+    Ok(values)
+}
+
+/// Decode a single column of a record, skipping over the columns before it without decoding
+/// them. Returns `None` if the record has fewer columns.
+pub(crate) fn parse_record_column(
+    bytes: &[u8],
+    col_idx: usize,
+    encoding: TextEncoding,
+) -> Result<Option<Value>, SQLiteInternalError> {
+    record_column_bytes(bytes, col_idx)?
+        .map(|(serial_type, col_bytes)| decode_value(serial_type, col_bytes, encoding))
+        .transpose()
+}
+
+/// Get the serial type and the undecoded bytes of a single column of a record, borrowed from it.
+/// Returns `None` if the record has fewer columns.
+pub(crate) fn record_column_bytes(
+    bytes: &[u8],
+    col_idx: usize,
+) -> Result<Option<(u64, &[u8])>, SQLiteInternalError> {
+    let (columns_serial_types, header_size) = parse_record_header(bytes)?;
+    let Some(&serial_type) = columns_serial_types.get(col_idx) else {
+        return Ok(None);
+    };
+
+    let mut body_offset = header_size;
+    for &previous_serial_type in &columns_serial_types[..col_idx] {
+        body_offset += column_bytes(bytes, body_offset, previous_serial_type)?.len();
+    }
+    Ok(Some((
+        serial_type,
+        column_bytes(bytes, body_offset, serial_type)?,
+    )))
+}
+
+/// Parse the header of a record: the serial type of each column, and the header size, i.e. the
+/// offset of the body.
+///
+/// The header size counts the varint holding it, which takes 2 bytes or more once the header is
+/// over 127 bytes, e.g. for a table of a few hundred columns: the serial types start right after
+/// it, whatever its length.
+fn parse_record_header(bytes: &[u8]) -> Result<(Vec<u64>, usize), SQLiteInternalError> {
+    let (header_size, header_size_varint) =
+        decode_varint(bytes).ok_or(SQLiteInternalError::MalformedRecord)?;
+    let header_size = header_size as usize;
+    if header_size > bytes.len() {
+        return Err(SQLiteInternalError::MalformedRecord);
+    }
+
+    // Array of the serial types
+    let mut columns_serial_types = Vec::new();
+    let mut header_offset = header_size_varint; // we already read the bytes for the header-size varint itself
+    while header_offset < header_size {
+        let (serial_type, varint_size) = decode_varint(&bytes[header_offset..header_size])
+            .ok_or(SQLiteInternalError::MalformedRecord)?;
+        columns_serial_types.push(serial_type);
+        header_offset += varint_size;
+    }
+
+    Ok((columns_serial_types, header_size))
+}
+
+/// Get the bytes of the column starting at `body_offset`. The length given by the serial type is
+/// checked against the remaining bytes of the record first: a corrupt serial type may claim a
+/// length way past the end of the record, even past the addressable range.
+fn column_bytes(
+    bytes: &[u8],
+    body_offset: usize,
+    serial_type: u64,
+) -> Result<&[u8], SQLiteInternalError> {
+    let byte_length = serial_type_2_byte_length(serial_type)?;
+    let remaining = bytes.len().saturating_sub(body_offset);
+    if byte_length > remaining as u64 {
+        return Err(SQLiteInternalError::MalformedRecord);
+    }
+    Ok(&bytes[body_offset..body_offset + byte_length as usize])
+}
+
+fn serial_type_2_byte_length(serial_type: u64) -> Result<u64, SerialTypeError> {
+    match serial_type {
+        0..5 => Ok(serial_type),
+        5 => Ok(6),
+        6 | 7 => Ok(8),
+        8 | 9 => Ok(0),
+        // 'Reserved for internal use. These serial type codes will never appear in a well-formed
+        // database file'
+        10 | 11 => Err(SerialTypeError::ReservedSerialNumber(serial_type)),
+        n if n >= 12 && n.rem_euclid(2) == 0 => Ok((n - 12) / 2),
+        n if n >= 13 && n.rem_euclid(2) == 1 => Ok((n - 13) / 2),
+        _ => Err(SerialTypeError::BadSerialNumber(serial_type)),
+    }
+}
+
+/// Decode the bytes of a column according to its serial type.
+///
+/// See the record format doc: https://www.sqlite.org/fileformat2.html#record_format
+fn decode_value(
+    serial_type: u64,
+    bytes: &[u8],
+    encoding: TextEncoding,
+) -> Result<Value, SQLiteInternalError> {
+    #[cfg(test)]
+    DECODED_VALUES.with(|decoded_values| decoded_values.set(decoded_values.get() + 1));
+
+    let value = match serial_type {
+        0 => Value::Null,
+        // Big-endian twos-complement integers of 1, 2, 3, 4, 6 or 8 bytes:
+        // the first byte is sign-extended, the following ones are shifted in.
+        1..=6 => Value::Integer(
+            bytes[1..]
+                .iter()
+                .fold(i64::from(bytes[0] as i8), |acc, &byte| {
+                    (acc << 8) | i64::from(byte)
+                }),
+        ),
+        7 => Value::Real(f64::from_be_bytes(
+            bytes[..8].try_into().expect("slice should have 8 bytes"),
+        )),
+        8 => Value::Integer(0),
+        9 => Value::Integer(1),
+        n if n >= 12 && n.rem_euclid(2) == 0 => Value::Blob(bytes.to_vec()),
+        n if n >= 13 && n.rem_euclid(2) == 1 => Value::Text(decode_text(bytes, encoding)?),
+        _ => return Err(SerialTypeError::BadSerialNumber(serial_type).into()),
+    };
+    Ok(value)
+}
+
+/// Decode the bytes of a text value. UTF-16 text is made of 2-byte code units, in the byte order
+/// of the encoding.
+fn decode_text(bytes: &[u8], encoding: TextEncoding) -> Result<String, SQLiteInternalError> {
+    let from_bytes = match encoding {
+        TextEncoding::Utf8 => return Ok(String::from_utf8(bytes.to_vec())?),
+        TextEncoding::Utf16le => u16::from_le_bytes,
+        TextEncoding::Utf16be => u16::from_be_bytes,
+    };
+    if bytes.len() % 2 != 0 {
+        return Err(SQLiteInternalError::MalformedRecord);
+    }
+    let code_units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    Ok(String::from_utf16(&code_units)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_record_decodes_mixed_types() {
+        let mut record = vec![
+            7,  // header size
+            0,  // NULL
+            1,  // 8-bit integer
+            7,  // 64-bit float
+            8,  // the integer 0
+            19, // text of 3 bytes
+            16, // blob of 2 bytes
+        ];
+        record.push(42);
+        record.extend_from_slice(&1.5f64.to_be_bytes());
+        record.extend_from_slice(b"abc");
+        record.extend_from_slice(&[0xde, 0xad]);
+
+        assert_eq!(
+            parse_record(&record, TextEncoding::Utf8).unwrap(),
+            vec![
+                Value::Null,
+                Value::Integer(42),
+                Value::Real(1.5),
+                Value::Integer(0),
+                Value::Text("abc".to_string()),
+                Value::Blob(vec![0xde, 0xad]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_serial_types_are_errors() {
+        for serial_type in [10, 11] {
+            // An 8-bit integer, then a column of the reserved serial type
+            let record = [3, 1, serial_type, 42];
+            let is_reserved = |err: Option<SQLiteInternalError>| {
+                matches!(
+                    err,
+                    Some(SQLiteInternalError::SerialTypeError(
+                        SerialTypeError::ReservedSerialNumber(n)
+                    )) if n == u64::from(serial_type)
+                )
+            };
+            assert!(is_reserved(parse_record(&record, TextEncoding::Utf8).err()));
+            let decoded_cols = [true, false];
+            assert!(is_reserved(
+                parse_record_columns(&record, &decoded_cols, TextEncoding::Utf8).err()
+            ));
+            assert!(is_reserved(
+                parse_record_column(&record, 1, TextEncoding::Utf8).err()
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_record_rejects_a_body_shorter_than_its_header_claims() {
+        // A 3-byte text, of which only 2 bytes are there
+        let record = [2, 19, b'a', b'b'];
+        assert!(matches!(
+            parse_record(&record, TextEncoding::Utf8),
+            Err(SQLiteInternalError::MalformedRecord)
+        ));
+    }
+
+    #[test]
+    fn absurd_text_length_is_a_malformed_record() {
+        // A text of 2^40 bytes: serial type 13 + 2 * 2^40, as a 6-byte varint
+        let mut record = vec![7, 0xc0, 0x80, 0x80, 0x80, 0x80, 0x0d];
+        record.extend_from_slice(b"hi");
+        assert!(matches!(
+            parse_record(&record, TextEncoding::Utf8),
+            Err(SQLiteInternalError::MalformedRecord)
+        ));
+
+        // The largest serial type, a blob of about 2^63 bytes
+        let mut record = vec![10];
+        record.extend_from_slice(&[0xff; 9]);
+        assert!(matches!(
+            parse_record_column(&record, 0, TextEncoding::Utf8),
+            Err(SQLiteInternalError::MalformedRecord)
+        ));
+    }
+
+    #[test]
+    fn header_size_varint_of_2_bytes() {
+        // 200 8-bit integers: the header takes 2 + 200 = 202 bytes, written as the varint 0x81 0x4a
+        let mut record = vec![0x81, 0x4a];
+        record.extend_from_slice(&[1; 200]);
+        record.extend((0..200).map(|n| (n % 100) as u8));
+
+        let values = parse_record(&record, TextEncoding::Utf8).unwrap();
+        assert_eq!(values.len(), 200);
+        assert_eq!(values[0], Value::Integer(0));
+        assert_eq!(values[199], Value::Integer(99));
+        assert_eq!(
+            parse_record_column(&record, 150, TextEncoding::Utf8).unwrap(),
+            Some(Value::Integer(50))
+        );
+    }
+}
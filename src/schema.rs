@@ -0,0 +1,341 @@
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use std::io::prelude::*;
+use std::str::FromStr;
+
+use crate::btree::{read_table_leaf_cell, PageLayout};
+#[cfg(not(feature = "regex"))]
+use crate::query::parse_create_index;
+use crate::query::{parse_create_table, parse_default_value};
+use crate::record::parse_record;
+use crate::tokenizer::{tokenize, TokenKind};
+use crate::value::Affinity;
+use crate::{SQLiteInternalError, TextEncoding, Value};
+
+#[cfg(feature = "regex")]
+const INDEXED_COLS_FROM_CREATE_INDEX_STMT: &str =
+    r"(?is)^\s*CREATE\s+(?:UNIQUE\s+)?INDEX\s.*?\(\s*(.*?)\s*\)\s*$";
+
+/// The kind of object a schema row describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectType {
+    Table,
+    Index,
+    View,
+    Trigger,
+}
+
+impl FromStr for ObjectType {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<ObjectType, Self::Err> {
+        match input {
+            "table" => Ok(ObjectType::Table),
+            "index" => Ok(ObjectType::Index),
+            "view" => Ok(ObjectType::View),
+            "trigger" => Ok(ObjectType::Trigger),
+            _ => Err("Invalid object type: {input}".to_string()),
+        }
+    }
+}
+
+/// A row of the schema table: https://www.sqlite.org/schematab.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaRow {
+    pub object_type: ObjectType,
+    pub name: String,
+    pub tbl_name: String, // the table an index or trigger belongs to, the object itself otherwise
+    pub root_page: u32,   // 0 for views and triggers, which have no b-tree
+    pub sql: String,      // the CREATE statement, empty for automatic indexes
+}
+
+/// A row of the 'sqlite_stat1' table, written by ANALYZE: statistics the query planner uses to
+/// choose between indexes. See https://www.sqlite.org/fileformat2.html#stat1tab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stat1Row {
+    pub tbl: String,
+    pub idx: Option<String>, // None for the row counting the rows of a table without index
+    /// Space-separated integers: the number of rows of the index, then for each of its leading
+    /// column prefixes the average number of rows sharing the same values, e.g. '10000 4 1'
+    pub stat: String,
+}
+
+/// Parse the rows of the 'sqlite_schema' table, given the location of its leaf cells.
+/// See the 'sql schema table' doc: https://www.sqlite.org/schematab.html
+///
+/// Page 1 is only the root of the schema b-tree: with a few hundred schema objects, it becomes an
+/// interior page, and the leaf cells are spread over other pages. They are located by the same
+/// traversal as for any table, see `table_leaf_cells`.
+pub(crate) fn parse_schema_table(
+    leaf_cells: Vec<(u64, u16)>,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+    db: &mut (impl Read + Seek),
+) -> Result<Vec<SchemaRow>, SQLiteInternalError> {
+    leaf_cells
+        .into_iter()
+        .map(|(page_offset, cell_offset)| {
+            parse_sql_schema_table_cell(page_offset, cell_offset, page_layout, encoding, db)
+        })
+        .collect()
+}
+
+/// Find the schema row of a table.
+///
+/// The schema table itself is not described in the schema table, but can be queried as well, as
+/// 'sqlite_schema' or by its historical name 'sqlite_master'. Its root page is always page 1.
+pub(crate) fn find_table(schema_rows: &[SchemaRow], table_name: &str) -> Option<SchemaRow> {
+    if ["sqlite_schema", "sqlite_master"]
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(table_name))
+    {
+        return Some(SchemaRow {
+            object_type: ObjectType::Table,
+            name: table_name.to_string(),
+            tbl_name: table_name.to_string(),
+            root_page: 1,
+            sql: "CREATE TABLE sqlite_schema(type text, name text, tbl_name text, rootpage integer, sql text)".to_string(),
+        });
+    }
+
+    schema_rows
+        .iter()
+        .find(|row| row.object_type == ObjectType::Table && row.name == table_name)
+        .cloned()
+}
+
+/// Find the schema row of a view.
+pub(crate) fn find_view(schema_rows: &[SchemaRow], view_name: &str) -> Option<SchemaRow> {
+    schema_rows
+        .iter()
+        .find(|row| row.object_type == ObjectType::View && row.name == view_name)
+        .cloned()
+}
+
+/// Parse one cell in the 'sql_schema' table, at a given cell offset.
+///
+/// See the 'sql schema table' doc: https://www.sqlite.org/schematab.html
+///
+/// The record holds 5 columns: type, name, tbl_name, rootpage, sql. The CREATE statement of a
+/// wide table may not fit in the cell: the rest of the record is then read from overflow pages,
+/// like for any table row.
+fn parse_sql_schema_table_cell(
+    page_offset: u64,
+    cell_offset: u16,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+    db: &mut (impl Read + Seek),
+) -> Result<SchemaRow, SQLiteInternalError> {
+    let (_rowid, payload) = read_table_leaf_cell(page_offset, cell_offset, page_layout, db)?;
+    let mut values = parse_record(&payload, encoding)?.into_iter();
+
+    let mut next_text = || match values.next() {
+        Some(Value::Text(s)) => Ok(s),
+        Some(Value::Null) => Ok(String::new()), // e.g. 'sql' is NULL for auto-indexes
+        Some(Value::Integer(n)) => Ok(n.to_string()), // 'rootpage'
+        _ => Err(SQLiteInternalError::MalformedRecord),
+    };
+
+    let object_type =
+        ObjectType::from_str(&next_text()?).map_err(SQLiteInternalError::FoundBadObjectType)?;
+    let name = next_text()?;
+    let tbl_name = next_text()?;
+    let root_page = next_text()?
+        .parse::<u32>()
+        .map_err(|_| SQLiteInternalError::MalformedRecord)?;
+
+    // NOTE: the `sql` column corresponds to CREATE statements
+    // This is what we want to parse to extract the current table , e.g. 'apples', describe in this
+    // cell of the sql_schema table
+    let sql = next_text()?;
+
+    Ok(SchemaRow {
+        object_type,
+        name,
+        tbl_name,
+        root_page,
+        sql,
+    })
+}
+
+// Hex notes
+//
+// ec0 -> 14*(16*16) + 12*16 + 0 = 3584 + 192 + 0 = 3776
+
+/// Parse column names from a 'CREATE' SQL statement.
+///
+/// Example: 'CREATE TABLE apples\n(\n\tid integer primary key autoincrement,\n\tname text,\n\tcolor
+/// text\n)' -> 'id', 'name', 'color'
+pub(crate) fn col_names_from_sql_create_stmt(
+    sql_create_stmt: &str,
+) -> Result<Vec<String>, SQLiteInternalError> {
+    Ok(parse_create_table(sql_create_stmt)
+        .map_err(SQLiteInternalError::BadCreateStatement)?
+        .columns
+        .into_iter()
+        .map(|column_def| column_def.name)
+        .collect())
+}
+
+/// Find the default value of each column in a 'CREATE' SQL statement: its DEFAULT literal, or
+/// NULL.
+///
+/// A record holds fewer values than the table has columns when columns were added with 'ALTER
+/// TABLE ... ADD COLUMN' after it was written: the missing trailing values are the defaults.
+///
+/// Example: 'CREATE TABLE t(a, b INTEGER DEFAULT 7)' -> NULL, 7
+pub(crate) fn default_values_from_sql_create_stmt(sql_create_stmt: &str) -> Vec<Value> {
+    let Ok(create_table) = parse_create_table(sql_create_stmt) else {
+        return Vec::new();
+    };
+    create_table
+        .columns
+        .iter()
+        .map(|column_def| {
+            column_def
+                .default
+                .as_deref()
+                .and_then(parse_default_value)
+                .unwrap_or(Value::Null)
+        })
+        .collect()
+}
+
+/// Fill in the values missing at the end of a short record with the column defaults, see
+/// `default_values_from_sql_create_stmt`.
+pub(crate) fn fill_missing_values(record: &mut Vec<Value>, default_values: &[Value]) {
+    if let Some(missing_values) = default_values.get(record.len()..) {
+        record.extend_from_slice(missing_values);
+    }
+}
+
+/// Find the declared type of each column in a 'CREATE' SQL statement, as written, if any.
+///
+/// Example: 'CREATE TABLE t(a, b VARCHAR(10))' -> None, 'VARCHAR(10)'
+pub(crate) fn declared_types_from_sql_create_stmt(sql_create_stmt: &str) -> Vec<Option<String>> {
+    let Ok(create_table) = parse_create_table(sql_create_stmt) else {
+        return Vec::new();
+    };
+    create_table
+        .columns
+        .into_iter()
+        .map(|column_def| column_def.type_name)
+        .collect()
+}
+
+/// Find the columns with REAL affinity in a 'CREATE' SQL statement, derived from their declared
+/// type, see `Affinity::from_type_name`.
+pub(crate) fn real_col_idxs_from_sql_create_stmt(sql_create_stmt: &str) -> Vec<usize> {
+    let Ok(create_table) = parse_create_table(sql_create_stmt) else {
+        return Vec::new();
+    };
+    create_table
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column_def)| {
+            let type_name = column_def.type_name.as_deref().unwrap_or_default();
+            Affinity::from_type_name(type_name) == Affinity::Real
+        })
+        .map(|(col_idx, _)| col_idx)
+        .collect()
+}
+
+/// Find the column that is an alias for the rowid in a 'CREATE' SQL statement, if any.
+///
+/// 'A PRIMARY KEY column only becomes an integer primary key if the declared type name is exactly
+/// "INTEGER"' (https://www.sqlite.org/lang_createtable.html#rowid). Such a column is stored as
+/// NULL in the records, its value is the rowid. The primary key must be on that column only.
+///
+/// Example: 'CREATE TABLE apples\n(\n\tid integer primary key autoincrement,\n\tname text,\n\tcolor
+/// text\n)' -> 0 ('id')
+pub(crate) fn rowid_alias_col_idx_from_sql_create_stmt(sql_create_stmt: &str) -> Option<usize> {
+    let columns = parse_create_table(sql_create_stmt).ok()?.columns;
+
+    let mut primary_key_col_idxs = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column_def)| column_def.primary_key)
+        .map(|(col_idx, _)| col_idx);
+    match (primary_key_col_idxs.next(), primary_key_col_idxs.next()) {
+        (Some(col_idx), None) => columns[col_idx]
+            .type_name
+            .as_deref()
+            .is_some_and(|type_name| type_name.eq_ignore_ascii_case("INTEGER"))
+            .then_some(col_idx),
+        _ => None,
+    }
+}
+
+/// Whether a 'CREATE INDEX' SQL statement creates a partial index, i.e. has a WHERE clause: its
+/// entries are only the rows matching the clause.
+///
+/// Indexes automatically created for UNIQUE and PRIMARY KEY constraints have an empty 'sql', and
+/// are not partial. A statement that can't be tokenized counts as partial, to be safe.
+pub(crate) fn is_partial_index(sql_create_index_stmt: &str) -> bool {
+    match tokenize(sql_create_index_stmt) {
+        Ok(tokens) => tokens.iter().any(|token| {
+            matches!(&token.kind, TokenKind::Identifier(word) if word.eq_ignore_ascii_case("WHERE"))
+        }),
+        Err(_) => true,
+    }
+}
+
+/// Parse the indexed column names from a 'CREATE INDEX' SQL statement, in key order.
+///
+/// Example: 'CREATE INDEX idx_companies_country\n\ton companies (country)' -> 'country'
+///
+/// Returns an empty list if the statement can't be parsed, e.g. for the empty 'sql' of indexes
+/// automatically created for UNIQUE and PRIMARY KEY constraints.
+#[cfg(feature = "regex")]
+pub(crate) fn indexed_col_names_from_sql_create_index_stmt(
+    sql_create_index_stmt: &str,
+) -> Vec<String> {
+    let re_indexed_cols = Regex::new(INDEXED_COLS_FROM_CREATE_INDEX_STMT)
+        .expect("creating new regex should not fail");
+
+    re_indexed_cols
+        .captures(sql_create_index_stmt)
+        .map(|caps| {
+            caps[1]
+                .split(',')
+                .filter_map(|col| col.split_whitespace().next())
+                .map(|col| col.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// See the 'regex' version above: without the 'regex' feature, the statement is parsed with the
+/// query tokenizer instead.
+#[cfg(not(feature = "regex"))]
+pub(crate) fn indexed_col_names_from_sql_create_index_stmt(
+    sql_create_index_stmt: &str,
+) -> Vec<String> {
+    parse_create_index(sql_create_index_stmt).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs with and without the 'regex' feature: both parsers give the same columns.
+    #[test]
+    fn indexed_columns_of_create_index_statements() {
+        assert_eq!(
+            indexed_col_names_from_sql_create_index_stmt(
+                "CREATE INDEX idx_fruits_color ON fruits (color)"
+            ),
+            ["color"]
+        );
+        assert_eq!(
+            indexed_col_names_from_sql_create_index_stmt(
+                "create unique index idx_pairs\n    on pairs ( a,  b DESC )"
+            ),
+            ["a", "b"]
+        );
+        // Automatic indexes have no statement
+        assert!(indexed_col_names_from_sql_create_index_stmt("").is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use std::io::{prelude::*, Cursor};
+
+use crate::btree::{local_table_leaf_payload, read_table_leaf_cell, PageLayout};
+use crate::record::{parse_record, parse_record_column, record_column_bytes};
+use crate::schema::{
+    col_names_from_sql_create_stmt, default_values_from_sql_create_stmt, fill_missing_values,
+    real_col_idxs_from_sql_create_stmt, rowid_alias_col_idx_from_sql_create_stmt, SchemaRow,
+};
+use crate::value::apply_real_affinity;
+use crate::{Database, SQLQueryError, SQLiteError, SQLiteInternalError, TextEncoding, Value};
+
+/// A row of a table: its rowid, and its values in table column order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub rowid: i64,
+    pub values: Vec<Value>,
+}
+
+/// How the values of a row are derived from its record, as declared by the CREATE statement of
+/// the table.
+#[derive(Debug)]
+struct ColumnLayout {
+    rowid_alias_col_idx: Option<usize>,
+    real_col_idxs: Vec<usize>, // columns with REAL affinity
+    default_values: Vec<Value>,
+}
+
+/// Read the row stored in a table leaf cell.
+fn read_row(
+    page_offset: u64,
+    cell_offset: u16,
+    column_layout: &ColumnLayout,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+    reader: &mut (impl Read + Seek),
+) -> Result<Row, SQLiteInternalError> {
+    let (rowid, payload) = read_table_leaf_cell(page_offset, cell_offset, page_layout, reader)?;
+    let mut values = parse_record(&payload, encoding)?;
+    // Columns added after the row was written are missing from its record
+    fill_missing_values(&mut values, &column_layout.default_values);
+    // An 'INTEGER PRIMARY KEY' column is stored as NULL: its value is the rowid
+    if let Some(alias_value) = column_layout
+        .rowid_alias_col_idx
+        .and_then(|idx| values.get_mut(idx))
+    {
+        *alias_value = Value::Integer(rowid);
+    }
+    for &col_idx in &column_layout.real_col_idxs {
+        if let Some(value) = values.get_mut(col_idx) {
+            apply_real_affinity(value);
+        }
+    }
+    Ok(Row { rowid, values })
+}
+
+/// A table of a database, borrowed from it.
+#[derive(Debug)]
+pub struct Table<'a, R: Read + Seek> {
+    database: &'a mut Database<R>,
+    table_name: String,
+    root_page: u32,
+    col_names: Vec<String>,
+    column_layout: ColumnLayout,
+    page_layout: PageLayout,
+    encoding: TextEncoding,
+}
+
+impl<'a, R: Read + Seek> Table<'a, R> {
+    pub(crate) fn new(
+        database: &'a mut Database<R>,
+        schema_row: &SchemaRow,
+    ) -> Result<Self, SQLiteError> {
+        let encoding = database.text_encoding()?;
+        let page_layout = PageLayout::read(&mut database.reader)?;
+        Ok(Table {
+            database,
+            table_name: schema_row.name.clone(),
+            root_page: schema_row.root_page,
+            col_names: col_names_from_sql_create_stmt(&schema_row.sql)?,
+            column_layout: ColumnLayout {
+                rowid_alias_col_idx: rowid_alias_col_idx_from_sql_create_stmt(&schema_row.sql),
+                real_col_idxs: real_col_idxs_from_sql_create_stmt(&schema_row.sql),
+                default_values: default_values_from_sql_create_stmt(&schema_row.sql),
+            },
+            page_layout,
+            encoding,
+        })
+    }
+
+    /// Page number of the root page of the table b-tree.
+    pub fn root_page(&self) -> u32 {
+        self.root_page
+    }
+
+    /// Names of the columns, in table order.
+    pub fn column_names(&self) -> &[String] {
+        &self.col_names
+    }
+
+    /// Fetch the row with the given rowid, if any, seeking it through the table b-tree instead of
+    /// scanning the table.
+    pub fn row_by_rowid(&mut self, rowid: i64) -> Result<Option<Row>, SQLiteError> {
+        let Some((page_offset, cell_offset)) = self
+            .database
+            .table_leaf_cell_by_rowid(self.root_page, rowid)?
+        else {
+            return Ok(None);
+        };
+        let row = read_row(
+            page_offset,
+            cell_offset,
+            &self.column_layout,
+            self.page_layout,
+            self.encoding,
+            &mut self.database.reader,
+        )?;
+        Ok(Some(row))
+    }
+
+    /// Stream the rows of the table, in rowid order.
+    pub fn rows(&mut self) -> impl Iterator<Item = Result<Row, SQLiteError>> + '_ {
+        let (leaf_cells, error) = match self.database.table_leaf_cells(self.root_page) {
+            Ok(leaf_cells) => (leaf_cells, None),
+            Err(err) => (Vec::new(), Some(SQLiteError::from(err))),
+        };
+
+        let column_layout = &self.column_layout;
+        let page_layout = self.page_layout;
+        let encoding = self.encoding;
+        let reader = &mut self.database.reader;
+        error.into_iter().map(Err).chain(leaf_cells.into_iter().map(
+            move |(page_offset, cell_offset)| {
+                Ok(read_row(
+                    page_offset,
+                    cell_offset,
+                    column_layout,
+                    page_layout,
+                    encoding,
+                    reader,
+                )?)
+            },
+        ))
+    }
+
+    /// Stream the rows of the table mapped into the caller's own type, in rowid order.
+    ///
+    /// Example: `table.rows_as(|row| Ok::<_, SQLiteError>((row.rowid, row.values[1].to_string())))`
+    pub fn rows_as<'t, T, E, F>(&'t mut self, f: F) -> impl Iterator<Item = Result<T, E>> + 't
+    where
+        E: From<SQLiteError>,
+        F: Fn(&Row) -> Result<T, E> + 't,
+    {
+        self.rows().map(move |row| f(&row?))
+    }
+
+    /// Stream the values of a single column, in rowid order.
+    ///
+    /// Only the requested column of each row is decoded. If the column does not exist, the only
+    /// item is an error.
+    pub fn column_values(
+        &mut self,
+        column_name: &str,
+    ) -> impl Iterator<Item = Result<Value, SQLiteError>> + '_ {
+        let located_cells = match self.column_index(column_name) {
+            Ok(col_idx) => self
+                .database
+                .table_leaf_cells(self.root_page)
+                .map(|leaf_cells| (col_idx, leaf_cells))
+                .map_err(SQLiteError::from),
+            Err(err) => Err(err.into()),
+        };
+        let (col_idx, leaf_cells, error) = match located_cells {
+            Ok((col_idx, leaf_cells)) => (col_idx, leaf_cells, None),
+            Err(err) => (0, Vec::new(), Some(err)),
+        };
+
+        let is_rowid_alias = self.column_layout.rowid_alias_col_idx == Some(col_idx);
+        let has_real_affinity = self.column_layout.real_col_idxs.contains(&col_idx);
+        let default_value = self.column_layout.default_values.get(col_idx).cloned();
+        let page_layout = self.page_layout;
+        let encoding = self.encoding;
+        let reader = &mut self.database.reader;
+        error.into_iter().map(Err).chain(leaf_cells.into_iter().map(
+            move |(page_offset, cell_offset)| {
+                let (rowid, payload) =
+                    read_table_leaf_cell(page_offset, cell_offset, page_layout, reader)?;
+                // An 'INTEGER PRIMARY KEY' column is stored as NULL: its value is the rowid
+                if is_rowid_alias {
+                    return Ok(Value::Integer(rowid));
+                }
+                // Columns added after the row was written are missing from its record
+                let mut value = parse_record_column(&payload, col_idx, encoding)?
+                    .or_else(|| default_value.clone())
+                    .ok_or(SQLiteInternalError::MalformedRecord)?;
+                if has_real_affinity {
+                    apply_real_affinity(&mut value);
+                }
+                Ok(value)
+            },
+        ))
+    }
+
+    fn column_index(&self, column_name: &str) -> Result<usize, SQLQueryError> {
+        self.col_names
+            .iter()
+            .position(|s| s == column_name)
+            .ok_or_else(|| SQLQueryError::NoSuchColumn {
+                column_name: column_name.to_string(),
+                table_name: self.table_name.clone(),
+            })
+    }
+}
+
+impl<'a> Table<'_, Cursor<&'a [u8]>> {
+    /// The bytes of a text or blob value of a row, borrowed from the bytes of a database read with
+    /// `Database::from_slice` instead of copied out of them, e.g. to hand a large blob over
+    /// without copying it. Text is left in the encoding of the database.
+    ///
+    /// Returns `None` if there is no row of that rowid, if the value is not stored as text nor as
+    /// a blob, or if the row spills onto overflow pages: its bytes are not contiguous then, and
+    /// only its owned values can be read, see `row_by_rowid`.
+    pub fn column_bytes(
+        &mut self,
+        rowid: i64,
+        column_name: &str,
+    ) -> Result<Option<&'a [u8]>, SQLiteError> {
+        let col_idx = self.column_index(column_name)?;
+        let Some((page_offset, cell_offset)) = self
+            .database
+            .table_leaf_cell_by_rowid(self.root_page, rowid)?
+        else {
+            return Ok(None);
+        };
+        let file: &'a [u8] = self.database.reader.get_ref();
+        let Some(payload) =
+            local_table_leaf_payload(file, page_offset, cell_offset, self.page_layout)?
+        else {
+            return Ok(None);
+        };
+        // Serial types from 12 up are blobs (even) and text (odd)
+        Ok(record_column_bytes(payload, col_idx)?
+            .filter(|&(serial_type, _)| serial_type >= 12)
+            .map(|(_, col_bytes)| col_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{fixture_bytes, open_fixture};
+    use crate::{Database, SQLQueryError, SQLiteError, Value};
+
+    #[test]
+    fn column_values_match_the_full_rows() {
+        let mut database = open_fixture("sample.db");
+        let mut table = database.table("apples").unwrap();
+        let colors = table
+            .column_values("color")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected_colors = ["Light Green", "Red", "Blush Red", "Yellow"];
+        assert_eq!(
+            colors,
+            expected_colors.map(|color| Value::Text(color.to_string()))
+        );
+
+        // 'id' is an alias for the rowid
+        let ids = table
+            .column_values("id")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ids, (1..=4).map(Value::Integer).collect::<Vec<_>>());
+
+        assert_eq!(table.column_values("weight").count(), 1);
+    }
+
+    #[test]
+    fn row_by_rowid_finds_existing_rows_only() {
+        // 'numbers' is spread over 63 leaves under an interior root page
+        let mut database = open_fixture("pages.db");
+        let mut table = database.table("numbers").unwrap();
+        for rowid in [1, 500, 1000] {
+            let row = table.row_by_rowid(rowid).unwrap().unwrap();
+            assert_eq!(row.rowid, rowid);
+            assert_eq!(
+                row.values,
+                vec![
+                    Value::Integer(rowid),
+                    Value::Text(format!("number {rowid} of the table"))
+                ]
+            );
+        }
+        for rowid in [0, 1001, -1] {
+            assert!(table.row_by_rowid(rowid).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn rows_map_into_a_struct() {
+        #[derive(Debug, PartialEq)]
+        struct Apple {
+            name: String,
+            color: String,
+        }
+
+        let mut database = open_fixture("sample.db");
+        let mut table = database.table("apples").unwrap();
+        let apples = table
+            .rows_as(|row| match &row.values[1..] {
+                [Value::Text(name), Value::Text(color)] => Ok(Apple {
+                    name: name.clone(),
+                    color: color.clone(),
+                }),
+                values => Err(SQLiteError::from(SQLQueryError::InvalidSQL(format!(
+                    "not an apple: {values:?}"
+                )))),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(apples.len(), 4);
+        assert_eq!(
+            apples[1],
+            Apple {
+                name: "Fuji".to_string(),
+                color: "Red".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn text_and_blob_bytes_are_borrowed_from_the_database_slice() {
+        let bytes = fixture_bytes("blobs.db");
+        let mut database = Database::from_slice(&bytes).unwrap();
+        let mut table = database.table("files").unwrap();
+
+        let data = table.column_bytes(2, "data").unwrap().unwrap();
+        assert_eq!(data, [b'1'; 1000]);
+        // Not a copy: the bytes lie in the source buffer
+        let source = bytes.as_ptr_range();
+        assert!(source.start <= data.as_ptr() && data.as_ptr_range().end <= source.end);
+        assert_eq!(
+            table.column_bytes(2, "name").unwrap(),
+            Some(&b"ones.bin"[..])
+        );
+
+        // The blob of row 1 spills onto overflow pages, 'id' is the rowid, and there is no row 3
+        assert_eq!(table.column_bytes(1, "data").unwrap(), None);
+        assert_eq!(table.column_bytes(2, "id").unwrap(), None);
+        assert_eq!(table.column_bytes(3, "data").unwrap(), None);
+        assert!(matches!(
+            table.column_bytes(2, "bogus"),
+            Err(SQLiteError::SQLiteQueryError(
+                SQLQueryError::NoSuchColumn { .. }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the unit tests.
+//!
+//! Tests read 'sample.db', at the root of the repository, and the databases of
+//! 'tests/fixtures', built by 'tests/fixtures/make_fixtures.sh'.
+
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+
+use crate::{Database, Value};
+
+/// Path of a test database, e.g. 'sample.db' or 'misc.db' for a fixture.
+pub(crate) fn fixture_path(name: &str) -> String {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    if name == "sample.db" {
+        format!("{dir}/{name}")
+    } else {
+        format!("{dir}/tests/fixtures/{name}")
+    }
+}
+
+/// Open a test database.
+pub(crate) fn open_fixture(name: &str) -> Database<BufReader<File>> {
+    Database::open(fixture_path(name)).expect("test database should open")
+}
+
+/// Read a test database into memory, e.g. to corrupt it.
+pub(crate) fn fixture_bytes(name: &str) -> Vec<u8> {
+    std::fs::read(fixture_path(name)).expect("test database should be readable")
+}
+
+/// Run a query, and return the values of its rows.
+pub(crate) fn query_rows(database: &mut Database<impl Read + Seek>, sql: &str) -> Vec<Vec<Value>> {
+    database
+        .query(sql, &[])
+        .unwrap_or_else(|err| panic!("query '{sql}' should run: {err}"))
+        .rows
+}
+
+pub(crate) fn text(text: &str) -> Value {
+    Value::Text(text.to_string())
+}
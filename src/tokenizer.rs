@@ -0,0 +1,178 @@
+use std::fmt;
+
+use thiserror::Error;
+
+/// Positions are byte offsets into the SQL query string, starting at 0.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TokenizerError {
+    #[error("unterminated string literal starting at position {}", .position)]
+    UnterminatedString { position: usize },
+    #[error("unexpected character '{}' at position {}", .character, .position)]
+    UnexpectedCharacter { character: char, position: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    Identifier(String),       // keywords are identifiers too, told apart by the parser
+    QuotedIdentifier(String), // "double-quoted", with "" escaping a quote, [bracketed] or `quoted`
+    String(String),           // 'single-quoted', with '' escaping a quote
+    Number(String),           // kept as written: 42, 0x2A, 4.2
+    Star,
+    Comma,
+    Dot,
+    Equals,
+    Minus,
+    LeftParen,
+    RightParen,
+    Semicolon,
+    Parameter,        // '?', a placeholder for a value bound when running the query
+    Operator(String), // any other operator, e.g. '<=' or '||'
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Identifier(identifier) => write!(f, "{identifier}"),
+            TokenKind::QuotedIdentifier(identifier) => {
+                write!(f, "\"{}\"", identifier.replace('"', "\"\""))
+            }
+            TokenKind::String(string) => write!(f, "'{}'", string.replace('\'', "''")),
+            TokenKind::Number(number) => write!(f, "{number}"),
+            TokenKind::Star => write!(f, "*"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::Equals => write!(f, "="),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::LeftParen => write!(f, "("),
+            TokenKind::RightParen => write!(f, ")"),
+            TokenKind::Semicolon => write!(f, ";"),
+            TokenKind::Parameter => write!(f, "?"),
+            TokenKind::Operator(operator) => write!(f, "{operator}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) position: usize,
+}
+
+/// Split a SQL query into tokens. Whitespace only separates tokens.
+pub(crate) fn tokenize(sql_query: &str) -> Result<Vec<Token>, TokenizerError> {
+    let mut tokens = Vec::new();
+    let mut chars = sql_query.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '*' => TokenKind::Star,
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
+            '=' => TokenKind::Equals,
+            '-' => TokenKind::Minus,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            ';' => TokenKind::Semicolon,
+            '?' => TokenKind::Parameter,
+            '[' => {
+                let mut identifier = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) => break,
+                        Some((_, c)) => identifier.push(c),
+                        None => return Err(TokenizerError::UnterminatedString { position }),
+                    }
+                }
+                TokenKind::QuotedIdentifier(identifier)
+            }
+            '\'' | '"' | '`' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote is an escaped quote, a single one ends the string
+                        Some((_, q)) if q == c && chars.next_if(|&(_, q)| q == c).is_some() => {
+                            string.push(c)
+                        }
+                        Some((_, q)) if q == c => break,
+                        Some((_, q)) => string.push(q),
+                        None => return Err(TokenizerError::UnterminatedString { position }),
+                    }
+                }
+                match c {
+                    '\'' => TokenKind::String(string),
+                    _ => TokenKind::QuotedIdentifier(string),
+                }
+            }
+            '+' | '/' | '%' | '<' | '>' | '!' | '|' | '&' | '~' => {
+                let mut operator = String::from(c);
+                let is_two_char_operator = |&(_, next): &(usize, char)| {
+                    matches!(
+                        (c, next),
+                        ('<', '=' | '>' | '<') | ('>', '=' | '>') | ('!', '=') | ('|', '|')
+                    )
+                };
+                if let Some((_, next)) = chars.next_if(is_two_char_operator) {
+                    operator.push(next);
+                }
+                TokenKind::Operator(operator)
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::from(c);
+                while let Some((_, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '.')
+                {
+                    number.push(c);
+                    // The exponent of a real may be signed, e.g. 2E-2
+                    let is_hex = number.starts_with("0x") || number.starts_with("0X");
+                    if matches!(c, 'e' | 'E') && !is_hex {
+                        if let Some((_, sign)) = chars.next_if(|&(_, c)| c == '+' || c == '-') {
+                            number.push(sign);
+                        }
+                    }
+                }
+                TokenKind::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut identifier = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_') {
+                    identifier.push(c);
+                }
+                TokenKind::Identifier(identifier)
+            }
+            character => {
+                return Err(TokenizerError::UnexpectedCharacter {
+                    character,
+                    position,
+                })
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unterminated_string_reports_where_it_starts() {
+        assert_eq!(
+            tokenize("SELECT name FROM apples WHERE name = 'Fuji"),
+            Err(TokenizerError::UnterminatedString { position: 37 })
+        );
+    }
+
+    #[test]
+    fn illegal_character_reports_its_position() {
+        assert_eq!(
+            tokenize("SELECT name FROM apples WHERE id # 1"),
+            Err(TokenizerError::UnexpectedCharacter {
+                character: '#',
+                position: 33
+            })
+        );
+    }
+}
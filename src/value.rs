@@ -0,0 +1,369 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A decoded column value. Variants follow the SQLite storage classes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// Compare two values the way SQLite's ORDER BY sorts them: by storage class first, NULL
+    /// before numbers before text before blobs, numbers being compared numerically with each
+    /// other whether integers or reals. See `compare_values`.
+    ///
+    /// This is not a `PartialOrd` implementation: 3 and 3.0 compare equal here, but are different
+    /// values for `PartialEq`.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        compare_values(self, other)
+    }
+}
+
+/// Render a value like sqlite3 does in list mode: NULL as an empty string, reals with up to 15
+/// significant digits, and blobs as hexadecimal.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Real(x) => write!(f, "{}", format_real(*x)),
+            Value::Text(text) => write!(f, "{text}"),
+            Value::Blob(bytes) => bytes.iter().try_for_each(|byte| write!(f, "{byte:02X}")),
+        }
+    }
+}
+
+/// Format a real like SQLite's '%!.15g': 15 significant digits, trailing zeros dropped but
+/// always with a decimal point, and scientific notation for exponents below -4 or above 14.
+///
+/// Examples: 3.14 -> '3.14', 100.0 -> '100.0', 1e20 -> '1.0e+20', 1e-5 -> '1.0e-05'
+fn format_real(x: f64) -> String {
+    if x.is_infinite() {
+        return if x > 0.0 { "Inf" } else { "-Inf" }.to_string();
+    }
+    // Like SQLite, negative zero has no sign
+    if x == 0.0 {
+        return "0.0".to_string();
+    }
+
+    // Rounding to 15 significant digits first, as it may carry over to the exponent
+    let scientific = format!("{x:.14e}");
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let exponent: i32 = exponent.parse().expect("the exponent is an integer");
+
+    if (-4..15).contains(&exponent) {
+        let fixed = format!("{x:.*}", (14 - exponent) as usize);
+        trim_fraction(&fixed)
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{sign}{:02}", trim_fraction(mantissa), exponent.abs())
+    }
+}
+
+/// Drop the trailing zeros of a decimal fraction, keeping at least one digit after the point.
+fn trim_fraction(decimal: &str) -> String {
+    if !decimal.contains('.') {
+        return format!("{decimal}.0");
+    }
+    let trimmed = decimal.trim_end_matches('0');
+    match trimmed.strip_suffix('.') {
+        Some(integral) => format!("{integral}.0"),
+        None => trimmed.to_string(),
+    }
+}
+
+/// Apply REAL affinity to a value read from a column: SQLite stores integral reals as integers to
+/// save space, e.g. 1500.0 as 1500, and turns them back into reals when reading them.
+pub(crate) fn apply_real_affinity(value: &mut Value) {
+    if let Value::Integer(n) = *value {
+        *value = Value::Real(n as f64);
+    }
+}
+
+/// A type affinity[1]: the storage class a column prefers, or a CAST converts to.
+///
+/// [1]: https://www.sqlite.org/datatype3.html#type_affinity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Derive the affinity of a declared type[1], from the words it contains, checked in order:
+    /// 'INT' gives INTEGER, 'CHAR', 'CLOB' or 'TEXT' give TEXT, 'BLOB' or no type give BLOB,
+    /// 'REAL', 'FLOA' or 'DOUB' give REAL, anything else gives NUMERIC.
+    ///
+    /// [1]: https://www.sqlite.org/datatype3.html#determination_of_column_affinity
+    pub(crate) fn from_type_name(type_name: &str) -> Affinity {
+        let type_name = type_name.to_ascii_uppercase();
+        let contains_any = |words: &[&str]| words.iter().any(|word| type_name.contains(word));
+        if contains_any(&["INT"]) {
+            Affinity::Integer
+        } else if contains_any(&["CHAR", "CLOB", "TEXT"]) {
+            Affinity::Text
+        } else if type_name.is_empty() || contains_any(&["BLOB"]) {
+            Affinity::Blob
+        } else if contains_any(&["REAL", "FLOA", "DOUB"]) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Convert a value like `CAST(<value> AS <type>)` does, for a type of this affinity[1].
+    /// NULL stays NULL.
+    ///
+    /// - INTEGER: reals are truncated towards zero, text and blobs give the integer they start
+    ///   with, e.g. '12abc' gives 12 and '1e3' gives 1, or 0. Out of range values are clamped.
+    /// - REAL: text and blobs give the number they start with, e.g. '1e3' gives 1000.0
+    /// - NUMERIC: like REAL, but text holding an integral number within range gives an integer
+    /// - TEXT: the value as text, blobs being read as UTF-8
+    /// - BLOB: the bytes of the value as text
+    ///
+    /// [1]: https://www.sqlite.org/lang_expr.html#castexpr
+    pub(crate) fn cast(self, value: Value) -> Value {
+        if value == Value::Null {
+            return Value::Null;
+        }
+        match self {
+            Affinity::Integer => match value {
+                Value::Integer(n) => Value::Integer(n),
+                // Rust's float to integer conversion saturates, like SQLite's
+                Value::Real(x) => Value::Integer(x as i64),
+                value => Value::Integer(leading_integer(&text_of(value))),
+            },
+            Affinity::Real => Value::Real(as_real(&to_numeric(value))),
+            Affinity::Numeric => match value {
+                value @ (Value::Integer(_) | Value::Real(_)) => value,
+                value => match to_numeric(value) {
+                    Value::Real(x)
+                        if x.fract() == 0.0 && x >= i64::MIN as f64 && x < -(i64::MIN as f64) =>
+                    {
+                        Value::Integer(x as i64)
+                    }
+                    value => value,
+                },
+            },
+            Affinity::Text => Value::Text(text_of(value)),
+            Affinity::Blob => match value {
+                Value::Blob(bytes) => Value::Blob(bytes),
+                value => Value::Blob(value.to_string().into_bytes()),
+            },
+        }
+    }
+}
+
+/// A non-NULL value as text, blobs being read as UTF-8.
+fn text_of(value: Value) -> String {
+    match value {
+        Value::Text(text) => text,
+        Value::Blob(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        value => value.to_string(),
+    }
+}
+
+/// The integer a text starts with, after any whitespace, clamped to the i64 range, or 0.
+fn leading_integer(text: &str) -> i64 {
+    let text = text.trim_start();
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let mut n: i64 = 0;
+    for digit in digits.bytes().take_while(u8::is_ascii_digit) {
+        let digit = i64::from(digit - b'0');
+        // Accumulate negative values: i64::MIN has no positive counterpart
+        n = n.saturating_mul(10).saturating_sub(digit);
+    }
+    if negative {
+        n
+    } else {
+        n.saturating_neg()
+    }
+}
+
+/// Compare two values the way ORDER BY sorts them.
+///
+/// Values of different storage classes are ordered by class: NULL, then INTEGER and REAL, then
+/// TEXT, then BLOB. Integers and reals are compared numerically with each other, so 3 equals 3.0.
+/// Text is compared with the BINARY collation.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        // -0.0 equals 0.0. SQLite never stores NaN (it becomes NULL), still order it consistently.
+        (Value::Real(a), Value::Real(b)) => a.partial_cmp(b).unwrap_or_else(|| a.total_cmp(b)),
+        (Value::Integer(a), Value::Real(b)) => compare_integer_real(*a, *b),
+        (Value::Real(a), Value::Integer(b)) => compare_integer_real(*b, *a).reverse(),
+        (Value::Text(a), Value::Text(b)) => binary_collation(a, b),
+        (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+        (a, b) => storage_class_rank(a).cmp(&storage_class_rank(b)),
+    }
+}
+
+/// Compare an integer with a real, without the precision loss of converting large integers to
+/// reals: integers above 2^53 are not all representable as reals.
+///
+/// Like SQLite's `sqlite3IntFloatCompare`: compare the integer with the truncated real first, and
+/// only then with the real itself.
+fn compare_integer_real(integer: i64, real: f64) -> Ordering {
+    // i64::MIN is exactly -2^63, and 2^63 is just past i64::MAX
+    if real < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    if real >= -(i64::MIN as f64) {
+        return Ordering::Less;
+    }
+    match integer.cmp(&(real as i64)) {
+        Ordering::Equal => (integer as f64)
+            .partial_cmp(&real)
+            .unwrap_or(Ordering::Equal),
+        ordering => ordering,
+    }
+}
+
+/// SQLite's default BINARY collation: compare the bytes of the text, ignoring locale. Uppercase
+/// letters sort before lowercase ones.
+///
+/// Text is decoded to UTF-8, whose byte order matches the order of the code points.
+pub(crate) fn binary_collation(a: &str, b: &str) -> Ordering {
+    a.as_bytes().cmp(b.as_bytes())
+}
+
+fn storage_class_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Integer(_) | Value::Real(_) => 1,
+        Value::Text(_) => 2,
+        Value::Blob(_) => 3,
+    }
+}
+
+/// Read a value as a number, like SQLite does for arithmetic operands: text, and blobs read as
+/// UTF-8 text, give the number they start with, e.g. '3.5kg' gives 3.5, or 0 if they start with
+/// no number. Leading whitespace is skipped, and hexadecimal is not read.
+pub(crate) fn to_numeric(value: Value) -> Value {
+    let text = match value {
+        Value::Text(text) => text,
+        Value::Blob(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        value => return value,
+    };
+    let text = text.trim_start();
+    let bytes = text.as_bytes();
+    let digits_end = |mut idx: usize| {
+        while bytes.get(idx).is_some_and(u8::is_ascii_digit) {
+            idx += 1;
+        }
+        idx
+    };
+
+    let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let integer_end = digits_end(end);
+    let mut has_digits = integer_end > end;
+    end = integer_end;
+    let mut is_real = false;
+    if bytes.get(end) == Some(&b'.') {
+        let fraction_end = digits_end(end + 1);
+        if has_digits || fraction_end > end + 1 {
+            has_digits = true;
+            is_real = true;
+            end = fraction_end;
+        }
+    }
+    if has_digits && matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign_len = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent_end = digits_end(end + 1 + sign_len);
+        if exponent_end > end + 1 + sign_len {
+            is_real = true;
+            end = exponent_end;
+        }
+    }
+    if !has_digits {
+        return Value::Integer(0);
+    }
+
+    let number = &text[..end];
+    match number.parse::<i64>() {
+        Ok(n) if !is_real => Value::Integer(n),
+        _ => Value::Real(number.parse().unwrap_or(0.0)),
+    }
+}
+
+/// A numeric value as a real, NULL being 0.
+pub(crate) fn as_real(value: &Value) -> f64 {
+    match *value {
+        Value::Integer(n) => n as f64,
+        Value::Real(x) => x,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_display_like_sqlite3() {
+        assert_eq!(Value::Null.to_string(), "");
+        assert_eq!(Value::Integer(-42).to_string(), "-42");
+        assert_eq!(Value::Real(2.5).to_string(), "2.5");
+        assert_eq!(Value::Real(100.0).to_string(), "100.0");
+        assert_eq!(Value::Real(0.1 + 0.2).to_string(), "0.3");
+        assert_eq!(Value::Real(1e20).to_string(), "1.0e+20");
+        assert_eq!(Value::Real(1e-5).to_string(), "1.0e-05");
+        assert_eq!(Value::Text("Fuji".to_string()).to_string(), "Fuji");
+        assert_eq!(Value::Blob(vec![0xca, 0xfe, 0x01]).to_string(), "CAFE01");
+    }
+
+    #[test]
+    fn integers_and_reals_compare_numerically() {
+        use Ordering::*;
+        assert_eq!(compare_values(&Value::Real(3.0), &Value::Integer(3)), Equal);
+        assert_eq!(compare_values(&Value::Integer(3), &Value::Real(3.0)), Equal);
+        assert_eq!(compare_values(&Value::Integer(3), &Value::Real(3.5)), Less);
+        assert_eq!(
+            compare_values(&Value::Real(-0.5), &Value::Integer(-1)),
+            Greater
+        );
+        // 2^53 + 1 is not representable as a real: it is still greater than 2^53
+        assert_eq!(
+            compare_values(&Value::Integer((1 << 53) + 1), &Value::Real(2f64.powi(53))),
+            Greater
+        );
+    }
+
+    #[test]
+    fn values_sort_by_storage_class_first() {
+        let mut values = vec![
+            Value::Blob(vec![0x00]),
+            Value::Text("abc".to_string()),
+            Value::Real(2.5),
+            Value::Integer(42),
+            Value::Null,
+            Value::Integer(-7),
+        ];
+        values.sort_by(Value::compare);
+        assert_eq!(
+            values,
+            [
+                Value::Null,
+                Value::Integer(-7),
+                Value::Real(2.5),
+                Value::Integer(42),
+                Value::Text("abc".to_string()),
+                Value::Blob(vec![0x00]),
+            ]
+        );
+    }
+}
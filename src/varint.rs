@@ -0,0 +1,143 @@
+use std::io::{prelude::*, SeekFrom};
+
+use crate::SQLiteInternalError;
+
+/// Reads the varint[1] using the Reader starting from the given offset.
+/// Uses a combination of bit-shifts, comparaisons and cast to from u8 to u64 to decode the varint.
+/// Reads 1 byte at a time from the varint: the reader should be buffered.
+///
+/// Returns:
+/// - the decoded varint as a u64
+/// - the size in bytes of this decoded varint
+///
+/// [1]: Protobuf documentation on varint encoding: https://protobuf.dev/programming-guides/encoding/#varints
+pub(crate) fn parse_varint(
+    offset: u64,
+    reader: &mut (impl Read + Seek),
+) -> Result<(u64, usize), SQLiteInternalError> {
+    reader
+        .seek(SeekFrom::Start(offset))
+        .map_err(SQLiteInternalError::SeekError)?;
+
+    // Parsing the varint
+    // Going byte by byte, checking the MSB for continuation
+    let mut varint_total: u64 = 0; // each byte contribution will be accumulated here
+
+    let mut varint_byte_idx: usize = 0; // idx into the varint bytes
+
+    // The Most Significant Byte (MSB) tells us about continuation:
+    // - 0 ~ false ~ end of the varint
+    // - 1 ~ true ~ varint continues onto the next byte
+    let mut msb: bool = true;
+
+    let mut varint_byte = [0; 1]; // current varint byte will be read into this buffer
+    while msb {
+        // NOTE: important assumption about the varint encoding.
+        // -> At most 9 bytes should be read.
+        assert!(varint_byte_idx < 9);
+
+        reader
+            .read_exact(&mut varint_byte)
+            .map_err(SQLiteInternalError::ReadError)?;
+
+        // The 9th byte, if reached, contributes all of its 8 bits (no continuation bit).
+        // This is how large and negative 64-bit values, e.g. negative rowids, are encoded.
+        if varint_byte_idx == 8 {
+            return Ok(((varint_total << 8) | u64::from(varint_byte[0]), 9));
+        }
+
+        // update MSB
+        msb = varint_byte[0] >= 0x80; // 0x80 = 1000_000 = 128
+
+        let contrib = u64::from(varint_byte[0]); // current byte contribution
+        varint_total = (varint_total << 7) + contrib;
+        if !msb {
+            return Ok((varint_total, varint_byte_idx + 1)); // MSB indicates this is the end of the
+                                                            // varint -> early return
+        }
+
+        varint_total -= 0x80; // 'dropping' the MSB (bit = 1) from the current byte
+                              // contribution we just  added
+        varint_byte_idx += 1;
+    }
+
+    Ok((varint_total, varint_byte_idx))
+}
+
+/// Decodes the varint at the start of the given bytes.
+///
+/// Unlike `parse_varint`, this works on an in-memory buffer (e.g. a cell payload).
+/// The first 8 bytes contribute their lower 7 bits, a 9th byte contributes all of its 8 bits.
+///
+/// Returns `None` if the bytes end before the varint does. Otherwise, returns:
+/// - the decoded varint as a u64
+/// - the size in bytes of this decoded varint
+///
+/// Examples: [0x7f] -> 127 (1 byte), [0x81, 0x00] -> 128 (2 bytes), [0xff; 9] -> u64::MAX (9 bytes)
+pub fn decode_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut varint_total: u64 = 0;
+    for (varint_byte_idx, &varint_byte) in bytes.iter().take(9).enumerate() {
+        if varint_byte_idx == 8 {
+            return Some(((varint_total << 8) | u64::from(varint_byte), 9));
+        }
+        varint_total = (varint_total << 7) | u64::from(varint_byte & 0x7f);
+        if varint_byte < 0x80 {
+            return Some((varint_total, varint_byte_idx + 1));
+        }
+    }
+    None
+}
+
+/// Decodes the varint at the start of the given bytes as a signed integer, like rowids: the 64
+/// bits are read as twos-complement.
+///
+/// Example: [0xff; 9] -> -1 (9 bytes)
+pub fn decode_signed_varint(bytes: &[u8]) -> Option<(i64, usize)> {
+    decode_varint(bytes).map(|(varint, varint_size)| (varint as i64, varint_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Varints and their values: 7 bits per byte, big-endian, and 8 bits in a 9th byte.
+    const ENCODINGS: [(&[u8], u64); 7] = [
+        (&[0x00], 0),
+        (&[0x7f], 127),
+        (&[0x81, 0x00], 128),
+        (&[0x82, 0x2c], 300),
+        (&[0xff, 0x7f], 16383),
+        (&[0x81, 0x80, 0x00], 16384),
+        (&[0xff; 9], u64::MAX),
+    ];
+
+    #[test]
+    fn decode_known_encodings() {
+        for (bytes, value) in ENCODINGS {
+            assert_eq!(decode_varint(bytes), Some((value, bytes.len())));
+            // Trailing bytes are not part of the varint
+            assert_eq!(
+                decode_varint(&[bytes, &[0x01]].concat()),
+                Some((value, bytes.len()))
+            );
+            assert_eq!(
+                parse_varint(0, &mut Cursor::new(bytes)).unwrap(),
+                (value, bytes.len())
+            );
+        }
+        // Ends before its last byte
+        assert_eq!(decode_varint(&[0x81, 0x80]), None);
+    }
+
+    #[test]
+    fn decode_signed_varints_as_twos_complement() {
+        assert_eq!(decode_signed_varint(&[0x82, 0x2c]), Some((300, 2)));
+        assert_eq!(decode_signed_varint(&[0xff; 9]), Some((-1, 9)));
+        let mut i64_min = [0x80; 9];
+        i64_min[0] = 0xc0;
+        i64_min[8] = 0x00;
+        assert_eq!(decode_signed_varint(&i64_min), Some((i64::MIN, 9)));
+    }
+}
@@ -0,0 +1,946 @@
+//! Tests of the command-line interface, running the built binary against the test databases.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use codecrafters_sqlite::{Database, PageType};
+
+/// Path of a test database: 'sample.db' at the root of the repository, or a fixture.
+fn db_path(name: &str) -> String {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    if name == "sample.db" {
+        format!("{dir}/{name}")
+    } else {
+        format!("{dir}/tests/fixtures/{name}")
+    }
+}
+
+/// Run the program with the given arguments, and with `stdin` as its input.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-sqlite"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("program should start");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(stdin.as_bytes())
+        .expect("program should read its input");
+    child.wait_with_output().expect("program should run")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn hex_literal_matches_its_decimal_value() {
+    let misc_db = db_path("misc.db");
+    let output = run(&[&misc_db, "select name from flags where flags = 0xFF"], "");
+    assert_eq!(stdout(&output), "all\n");
+
+    let output = run(&[&misc_db, "select name from flags where flags = 0X0f"], "");
+    assert_eq!(stdout(&output), "read\n");
+}
+
+#[test]
+fn literal_items_are_emitted_once_per_row() {
+    let sample_db = db_path("sample.db");
+    let output = run(&[&sample_db, "select 1 from apples"], "");
+    assert_eq!(stdout(&output), "1\n".repeat(4));
+
+    let output = run(&[&sample_db, "select 'hello', name from apples"], "");
+    assert_eq!(
+        stdout(&output),
+        "hello|Granny Smith\nhello|Fuji\nhello|Honeycrisp\nhello|Golden Delicious\n"
+    );
+}
+
+#[test]
+fn show_rowid_prefixes_each_row_with_its_rowid() {
+    let sample_db = db_path("sample.db");
+    let output = run(&["--show-rowid", &sample_db, "select name from apples"], "");
+    assert_eq!(
+        stdout(&output),
+        "1\tGranny Smith\n2\tFuji\n3\tHoneycrisp\n4\tGolden Delicious\n"
+    );
+
+    // The flag can be given anywhere
+    let sql = "select name, color from apples where color = 'Red'";
+    let output = run(&[&sample_db, "--show-rowid", sql], "");
+    assert_eq!(stdout(&output), "2\tFuji|Red\n");
+}
+
+#[test]
+fn table_scan_visits_the_rightmost_subtree() {
+    // 'numbers' has its root on page 2, an interior table page (0x05)
+    let pages_db = db_path("pages.db");
+    let bytes = std::fs::read(&pages_db).unwrap();
+    assert_eq!(bytes[512], 0x05);
+
+    // The last rows are in the subtree of the rightmost pointer
+    let output = run(&[&pages_db, "select label from numbers"], "");
+    let stdout = stdout(&output);
+    assert_eq!(stdout.lines().count(), 1000);
+    assert_eq!(stdout.lines().last(), Some("number 1000 of the table"));
+    let output = run(&[&pages_db, "SELECT COUNT(*) FROM numbers"], "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1000\n");
+}
+
+#[test]
+fn negative_integers_and_rowids_are_signed() {
+    let output = run(
+        &[
+            "--show-rowid",
+            &db_path("misc.db"),
+            "select amount from signed",
+        ],
+        "",
+    );
+    assert_eq!(
+        stdout(&output),
+        "-5\t-1\n-1\t-300000\n3\t-9223372036854775808\n"
+    );
+}
+
+#[test]
+fn count_of_an_indexed_column_matches_a_full_scan() {
+    // 'color' is indexed by 'idx_fruits_color', and NULL for 2 of the 7 fruits
+    let misc_db = db_path("misc.db");
+    let output = run(&[&misc_db, "select color from fruits"], "");
+    let nb_non_null = stdout(&output)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .count();
+    assert_eq!(nb_non_null, 5);
+
+    let output = run(&[&misc_db, "SELECT COUNT(color) FROM fruits"], "");
+    assert_eq!(stdout(&output), format!("{nb_non_null}\n"));
+}
+
+#[test]
+fn empty_table_has_no_rows() {
+    let misc_db = db_path("misc.db");
+    let output = run(&[&misc_db, "select * from empty"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    let output = run(&[&misc_db, "SELECT COUNT(*) FROM empty"], "");
+    assert_eq!(stdout(&output), "0\n");
+}
+
+#[test]
+fn in_subquery_keeps_the_rows_matching_its_values() {
+    // Order 9 matches no fruit, fruits without orders are left out
+    let misc_db = db_path("misc.db");
+    let sql = "select name from fruits where id in (select fruit_id from orders)";
+    let output = run(&[&misc_db, sql], "");
+    assert_eq!(stdout(&output), "apple\ncherry\n");
+
+    let sql = "select name from fruits where id in (2, 6, 42)";
+    let output = run(&[&misc_db, sql], "");
+    assert_eq!(stdout(&output), "banana\nlime\n");
+}
+
+#[test]
+fn auto_vacuum_database_is_read_around_its_pointer_map_pages() {
+    // The table spans the pointer-map pages 2 and 105
+    let autovacuum_db = db_path("autovacuum.db");
+    let bytes = std::fs::read(&autovacuum_db).unwrap();
+    assert!(bytes.len() / 512 > 105);
+
+    let output = run(&[&autovacuum_db, "select padding from wide_rows"], "");
+    assert_eq!(
+        stdout(&output),
+        format!("{}\n", "x".repeat(200)).repeat(250)
+    );
+    let output = run(&[&autovacuum_db, "SELECT COUNT(*) FROM wide_rows"], "");
+    assert_eq!(stdout(&output), "250\n");
+}
+
+#[test]
+fn table_name_may_be_qualified_by_the_main_schema() {
+    let sample_db = db_path("sample.db");
+    let output = run(
+        &[&sample_db, "SELECT name FROM main.apples WHERE id = 2"],
+        "",
+    );
+    assert_eq!(stdout(&output), "Fuji\n");
+
+    let output = run(&[&sample_db, "SELECT name FROM temp.apples"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown schema 'temp'"));
+}
+
+#[test]
+fn in_list_takes_escaped_and_double_quoted_strings() {
+    let misc_db = db_path("misc.db");
+    let output = run(
+        &[
+            "--show-rowid",
+            &misc_db,
+            "SELECT word FROM words WHERE word IN ('a', \"b\", 'c''s')",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "1\ta\n2\tb\n3\tc's\n");
+}
+
+#[test]
+fn order_by_sorts_text_with_the_binary_collation() {
+    let misc_db = db_path("misc.db");
+    let output = run(&[&misc_db, "SELECT word FROM words ORDER BY word"], "");
+    // Uppercase letters sort before lowercase ones
+    assert_eq!(stdout(&output), "Apple\nB\na\nb\nc's\nd\n");
+}
+
+#[test]
+fn composite_index_seek_keeps_the_matching_rows() {
+    let misc_db = db_path("misc.db");
+    let output = run(
+        &[
+            "--show-rowid",
+            &misc_db,
+            "SELECT name FROM fruits WHERE name = 'cherry' AND color = 'red'",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "3\tcherry\n");
+    let output = run(
+        &[&misc_db, "SELECT name FROM fruits WHERE color = 'yellow'"],
+        "",
+    );
+    assert_eq!(stdout(&output), "banana\nlemon\n");
+}
+
+#[test]
+fn width_fixes_the_column_widths() {
+    // Truncated, right-justified, then auto
+    let output = run(
+        &[&db_path("sample.db")],
+        ".mode column\n.width 3 -6 0\nSELECT name, id, color FROM apples WHERE color = 'Red';\n",
+    );
+    assert_eq!(
+        stdout(&output),
+        "nam      id  color\n\
+         ---  ------  -----\n\
+         Fuj       2  Red  \n"
+    );
+}
+
+#[test]
+fn show_rowid_in_column_mode_keeps_the_columns_aligned() {
+    let output = run(
+        &["--show-rowid", &db_path("sample.db")],
+        ".mode column\nSELECT name FROM apples WHERE color = 'Red';\n",
+    );
+    assert_eq!(
+        stdout(&output),
+        "rowid  name\n\
+         -----  ----\n\
+         2      Fuji\n"
+    );
+}
+
+#[test]
+fn schema_table_is_queryable_as_sqlite_master() {
+    let sample_db = db_path("sample.db");
+    let output = run(&[&sample_db, "SELECT name, type FROM sqlite_master"], "");
+    assert_eq!(
+        stdout(&output),
+        "apples|table\nsqlite_sequence|table\noranges|table\n"
+    );
+    let output = run(&[&sample_db, "SELECT COUNT(*) FROM sqlite_schema"], "");
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn page_number_prints_the_root_page_of_a_table() {
+    let sample_db = db_path("sample.db");
+    let output = run(&["--page-number", "oranges", &sample_db], "");
+    assert!(output.status.success());
+
+    let schema_root_page = run(
+        &[
+            &sample_db,
+            "SELECT rootpage FROM sqlite_master WHERE name = 'oranges'",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), stdout(&schema_root_page));
+    assert_eq!(stdout(&output), "4\n");
+}
+
+#[test]
+fn ndjson_writes_one_complete_object_per_line() {
+    let output = run(
+        &[&db_path("sample.db")],
+        ".mode ndjson\nSELECT id, name FROM apples WHERE color IN ('Red', 'Yellow');\n",
+    );
+    assert_eq!(
+        stdout(&output),
+        "{\"id\":2,\"name\":\"Fuji\"}\n{\"id\":4,\"name\":\"Golden Delicious\"}\n"
+    );
+}
+
+#[test]
+fn view_runs_its_select_statement() {
+    let views_db = db_path("views.db");
+    let output = run(&[&views_db, "SELECT name FROM red_fruits"], "");
+    assert_eq!(stdout(&output), "apple\ncherry\n");
+    let output = run(&[&views_db, "SELECT COUNT(*) FROM red_fruits"], "");
+    assert_eq!(stdout(&output), "2\n");
+}
+
+#[test]
+fn view_with_a_join_is_not_supported() {
+    let output = run(&[&db_path("views.db"), "SELECT * FROM fruit_orders"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not implemented yet"));
+}
+
+#[test]
+fn unknown_table_is_an_error() {
+    for sql in ["SELECT * FROM nope", "SELECT COUNT(*) FROM nope"] {
+        let output = run(&[&db_path("views.db"), sql], "");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("no such table: nope"));
+    }
+}
+
+#[test]
+fn tables_command_lists_views_along_with_tables() {
+    let output = run(&[&db_path("views.db"), ".tables"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "fruits orders red_fruits fruit_orders\n");
+}
+
+#[test]
+fn trace_logs_each_page_read_to_stderr() {
+    // 'numbers' spans 64 pages: its interior root page 2 and 63 leaves
+    let output = run(
+        &[
+            "--trace",
+            &db_path("pages.db"),
+            "SELECT COUNT(*) FROM numbers",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1000\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let trace_lines = stderr
+        .lines()
+        .filter(|line| line.starts_with("trace: read page "))
+        .collect::<Vec<_>>();
+    assert!(trace_lines.contains(&"trace: read page 2 (InteriorTable, 62 cells)"));
+    let nb_leaf_reads = trace_lines
+        .iter()
+        .filter(|line| line.contains("(LeafTable, "))
+        .count();
+    // The leaves of 'numbers', and the schema page 1
+    assert_eq!(nb_leaf_reads, 63 + 1);
+}
+
+#[test]
+fn glob_predicate_is_case_sensitive() {
+    let misc_db = db_path("misc.db");
+    let output = run(
+        &[&misc_db, "SELECT word FROM words WHERE word GLOB 'b*'"],
+        "",
+    );
+    assert_eq!(stdout(&output), "b\n");
+    let output = run(
+        &[&misc_db, "SELECT word FROM words WHERE word LIKE 'b%'"],
+        "",
+    );
+    assert_eq!(stdout(&output), "b\nB\n");
+}
+
+#[test]
+fn scientific_notation_compares_with_real_columns() {
+    let reals_db = db_path("reals.db");
+    for (sql, name) in [
+        ("SELECT name FROM measures WHERE value = 1.5e3", "large"),
+        ("SELECT name FROM measures WHERE value = 2E-2", "tiny"),
+        ("SELECT name FROM measures WHERE value = 6.02E+23", "huge"),
+    ] {
+        assert_eq!(stdout(&run(&[&reals_db, sql], "")), format!("{name}\n"));
+    }
+    let output = run(&[&reals_db, "SELECT value FROM measures"], "");
+    // Like sqlite3
+    assert_eq!(stdout(&output), "0.02\n1.5\n1500.0\n6.02e+23\n");
+}
+
+#[test]
+fn rowid_lookup_reads_only_the_pages_on_its_path() {
+    // The root page 2 of 'numbers' and one of its 63 leaves
+    let pages_db = db_path("pages.db");
+    for sql in [
+        "SELECT label FROM numbers WHERE rowid = 5",
+        "SELECT label FROM numbers WHERE id = 5",
+    ] {
+        let output = run(&["--limit-pages", "2", &pages_db, sql], "");
+        assert_eq!(stdout(&output), "number 5 of the table\n");
+    }
+    // A scan reads every leaf
+    let output = run(
+        &[
+            "--limit-pages",
+            "2",
+            &pages_db,
+            "SELECT id FROM numbers WHERE label = 'number 5 of the table'",
+        ],
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("visited more than 2 pages"));
+}
+
+#[test]
+fn tableinfo_lists_the_columns_of_a_table() {
+    let output = run(&[&db_path("sample.db"), ".tableinfo apples"], "");
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "number of columns: 3\ncolumns: id name color\n"
+    );
+
+    let output = run(&[&db_path("sample.db"), ".tableinfo nope"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no such table: nope"));
+}
+
+#[test]
+fn rowid_range_reads_only_the_leaves_it_spans() {
+    // Rowids 10 to 20 are on the first two leaves of 'numbers', of 17 rows each
+    let pages_db = db_path("pages.db");
+    let ids = |first: i64, last: i64| {
+        (first..=last)
+            .map(|id| format!("{id}\n"))
+            .collect::<String>()
+    };
+    let output = run(
+        &[
+            "--limit-pages",
+            "3",
+            &pages_db,
+            "SELECT id FROM numbers WHERE rowid >= 10 AND rowid <= 20",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), ids(10, 20));
+    let output = run(
+        &[
+            "--limit-pages",
+            "3",
+            &pages_db,
+            "SELECT id FROM numbers WHERE id > 995",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), ids(996, 1000));
+}
+
+#[test]
+fn cmd_runs_before_the_command() {
+    let sample_db = db_path("sample.db");
+    let output = run(
+        &[
+            "-cmd",
+            ".mode ndjson",
+            &sample_db,
+            "SELECT id, name FROM apples WHERE id < 3",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "{\"id\":1,\"name\":\"Granny Smith\"}\n{\"id\":2,\"name\":\"Fuji\"}\n"
+    );
+
+    // Then the commands read from stdin
+    let output = run(
+        &[
+            "-batch",
+            "-cmd",
+            "SELECT name FROM oranges WHERE id = 1",
+            "-c",
+            "SELECT name FROM oranges WHERE id = 2",
+            &sample_db,
+        ],
+        "SELECT COUNT(*) FROM apples;\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Mandarin\nTangelo\n4\n");
+}
+
+#[test]
+fn whitespace_command_is_a_missing_command() {
+    for command in ["", "   ", "\t\n"] {
+        let output = run(&[&db_path("sample.db"), command], "");
+        assert_eq!(stdout(&output), "");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "Error: missing command: expected a SQL query or a dot command\n"
+        );
+    }
+}
+
+#[test]
+fn dbinfo_counts_the_tables_of_a_multi_page_schema() {
+    let many_tables_db = db_path("many_tables.db");
+    let schema_root = Database::open(&many_tables_db)
+        .unwrap()
+        .page_info(1)
+        .unwrap();
+    assert_eq!(schema_root.page_type, PageType::InteriorTable);
+
+    let output = run(&[&many_tables_db, ".dbinfo"], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("number of tables: 40\n"));
+}
+
+#[test]
+fn having_filters_out_groups() {
+    let output = run(
+        &[
+            &db_path("misc.db"),
+            "SELECT color, COUNT(*) FROM fruits GROUP BY color HAVING COUNT(*) > 1",
+        ],
+        "",
+    );
+    // 'green' has a single fruit
+    assert_eq!(stdout(&output), "|2\nred|2\nyellow|2\n");
+}
+
+#[test]
+fn union_all_concatenates_rows() {
+    let misc_db = db_path("misc.db");
+    let output = run(
+        &[
+            &misc_db,
+            "SELECT name FROM flags UNION ALL SELECT word FROM words",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "read\nall\nnone\na\nb\nc's\nd\nB\nApple\n");
+
+    let output = run(
+        &[
+            &misc_db,
+            "SELECT id, name FROM flags UNION ALL SELECT word FROM words",
+        ],
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("do not have the same number of result columns"));
+}
+
+#[test]
+fn json_count_is_a_number() {
+    let output = run(
+        &[&db_path("sample.db")],
+        ".mode json\nSELECT COUNT(*) FROM apples;\nSELECT COUNT(color) FROM apples;\n",
+    );
+    assert_eq!(
+        stdout(&output),
+        "[{\"COUNT(*)\":4}]\n[{\"COUNT(color)\":4}]\n"
+    );
+}
+
+#[test]
+fn encoding_flag_overrides_the_header() {
+    // A copy of the UTF-16le database, labeled as UTF-8
+    let mut bytes = std::fs::read(db_path("utf16le.db")).unwrap();
+    bytes[56..60].copy_from_slice(&1u32.to_be_bytes());
+    let mislabeled_db = std::env::temp_dir().join(format!("mislabeled-{}.db", std::process::id()));
+    std::fs::write(&mislabeled_db, bytes).unwrap();
+    let mislabeled_db = mislabeled_db.to_str().unwrap();
+
+    let sql = "SELECT greeting FROM greetings WHERE id = 2";
+    let output = run(&["--encoding", "utf-16le", mislabeled_db, sql], "");
+    assert_eq!(stdout(&output), "héllo wörld\n");
+    let output = run(&[mislabeled_db, sql], "");
+    assert_ne!(stdout(&output), "héllo wörld\n");
+    std::fs::remove_file(mislabeled_db).unwrap();
+}
+
+#[test]
+fn typeof_is_the_storage_class() {
+    let output = run(
+        &[&db_path("types.db"), "SELECT typeof(value) FROM mixed"],
+        "",
+    );
+    assert_eq!(
+        stdout(&output),
+        "null\ninteger\nreal\ntext\nblob\ninteger\ntext\n"
+    );
+}
+
+#[test]
+fn length_counts_characters_of_text_and_bytes_of_blobs() {
+    let output = run(
+        &[
+            &db_path("utf16le.db"),
+            "SELECT length(greeting) FROM greetings",
+        ],
+        "",
+    );
+    // 'héllo wörld' and '日本語' have multi-byte characters
+    assert_eq!(stdout(&output), "5\n11\n3\n");
+
+    let output = run(
+        &[&db_path("types.db"), "SELECT length(value) FROM mixed"],
+        "",
+    );
+    // NULL, 42, 2.5, 'text', x'00ff', -7 and ''
+    assert_eq!(stdout(&output), "\n2\n3\n4\n2\n2\n0\n");
+}
+
+#[test]
+fn upper_and_lower_convert_text() {
+    let output = run(
+        &[
+            &db_path("sample.db"),
+            "SELECT upper(name), lower(name) FROM apples WHERE id = 1",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "GRANNY SMITH|granny smith\n");
+
+    let output = run(
+        &[
+            &db_path("types.db"),
+            "SELECT upper(value), lower(value) FROM mixed WHERE id < 4",
+        ],
+        "",
+    );
+    // NULL and numbers are unchanged
+    assert_eq!(stdout(&output), "|\n42|42\n2.5|2.5\n");
+}
+
+#[test]
+fn dbinfo_prints_user_version_and_application_id() {
+    let output = run(&[&db_path("versioned.db"), ".dbinfo"], "");
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    assert!(stdout.contains("user version: 7\n"));
+    assert!(stdout.contains("application id: 1936024932\n"));
+}
+
+#[test]
+fn where_column_need_not_be_selected() {
+    let sample_db = db_path("sample.db");
+    let output = run(
+        &[&sample_db, "SELECT name FROM apples WHERE color = 'Red'"],
+        "",
+    );
+    assert_eq!(stdout(&output), "Fuji\n");
+    let output = run(
+        &[
+            &sample_db,
+            "SELECT id FROM apples WHERE name = 'Honeycrisp' AND color = 'Blush Red'",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn rowcounts_prints_the_row_count_of_each_table() {
+    let output = run(&[&db_path("sample.db"), ".rowcounts"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "apples: 4\noranges: 6\n");
+}
+
+#[test]
+fn arithmetic_on_columns() {
+    let misc_db = db_path("misc.db");
+    let output = run(
+        &[
+            &misc_db,
+            "SELECT flags * 2, flags / 2, flags - 1 + id FROM flags",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "30|7|15\n510|127|256\n0|0|2\n");
+    // i64::MIN * 2 overflows into a real
+    let output = run(
+        &[&misc_db, "SELECT amount * 2 FROM signed WHERE id = 3"],
+        "",
+    );
+    assert_eq!(stdout(&output), "-1.84467440737096e+19\n");
+}
+
+#[test]
+fn abs_of_negative_values() {
+    let output = run(
+        &[
+            &db_path("misc.db"),
+            "SELECT abs(amount), abs(id) FROM signed",
+        ],
+        "",
+    );
+    // No integer is the absolute value of i64::MIN
+    assert_eq!(stdout(&output), "1|5\n300000|1\n9.22337203685478e+18|3\n");
+}
+
+#[test]
+fn cast_text_to_integer_and_real_to_text() {
+    let output = run(
+        &[
+            &db_path("types.db"),
+            "SELECT CAST(value AS INTEGER) FROM mixed WHERE id >= 3 AND id <= 4",
+        ],
+        "",
+    );
+    // 2.5 is truncated, and 'text' has no leading number
+    assert_eq!(stdout(&output), "2\n0\n");
+
+    let output = run(
+        &[
+            &db_path("reals.db"),
+            "SELECT CAST('12abc' AS INTEGER), CAST(value AS TEXT) FROM measures WHERE id = 3",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "12|1500.0\n");
+}
+
+#[test]
+fn columns_added_later_are_null_or_their_default_in_older_rows() {
+    // Rows 1 and 2 were inserted before 'priority' and 'status' were added
+    let altered_db = db_path("altered.db");
+    let output = run(&[&altered_db, "SELECT id, priority, status FROM tasks"], "");
+    assert_eq!(stdout(&output), "1||todo\n2||todo\n3|1|done\n");
+    let output = run(
+        &[&altered_db, "SELECT title FROM tasks WHERE status = 'todo'"],
+        "",
+    );
+    assert_eq!(stdout(&output), "write\ntest\n");
+}
+
+#[test]
+fn in_list_with_null_only_matches_equal_values() {
+    let misc_db = db_path("misc.db");
+    // Rows 1, 2 and 3: only 1 is in the list, NULL matches nothing
+    let output = run(
+        &[&misc_db, "SELECT id FROM flags WHERE id IN (1, NULL)"],
+        "",
+    );
+    assert_eq!(stdout(&output), "1\n");
+    let output = run(&[&misc_db, "SELECT id FROM flags WHERE id IN (NULL)"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    // Not even NULL values
+    let output = run(
+        &[
+            &misc_db,
+            "SELECT id FROM fruits WHERE color IN (NULL, 'green')",
+        ],
+        "",
+    );
+    assert_eq!(stdout(&output), "6\n");
+}
+
+#[test]
+fn depth_prints_the_btree_depth_of_a_table() {
+    let output = run(&[&db_path("pages.db"), ".depth numbers"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n");
+}
+
+#[test]
+fn dbinfo_on_a_file_that_is_not_a_database_fails_early() {
+    let manifest = format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"));
+    let output = run(&[&manifest, ".dbinfo"], "");
+    assert!(!output.status.success());
+    // No field of the header is printed
+    assert_eq!(stdout(&output), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("file is not a database"));
+}
+
+#[test]
+fn one_newline_per_row_and_none_for_no_rows() {
+    let sample_db = db_path("sample.db");
+    let output = run(
+        &[&sample_db, "SELECT id, name FROM apples WHERE id < 3"],
+        "",
+    );
+    assert_eq!(stdout(&output), "1|Granny Smith\n2|Fuji\n");
+    let output = run(
+        &[&sample_db],
+        ".mode csv\n.headers on\nSELECT id, name FROM apples WHERE id < 3;\n",
+    );
+    assert_eq!(stdout(&output), "id,name\n1,\"Granny Smith\"\n2,Fuji\n");
+
+    // Nothing at all, not even the CSV header
+    let no_rows = "SELECT id FROM apples WHERE id > 10;\n";
+    let output = run(&[&sample_db], no_rows);
+    assert_eq!(stdout(&output), "");
+    let output = run(&[&sample_db], &format!(".mode csv\n.headers on\n{no_rows}"));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn maxrows_truncates_query_output_in_the_repl() {
+    let output = run(
+        &["-interactive", &db_path("sample.db")],
+        ".maxrows 2\nSELECT name FROM apples;\n.maxrows 0\nSELECT name FROM apples WHERE id > 2;\n",
+    );
+    assert!(output.status.success());
+    let rows = stdout(&output).replace("sqlite> ", "");
+    assert_eq!(rows, "Granny Smith\nFuji\nHoneycrisp\nGolden Delicious\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let notes = stderr
+        .lines()
+        .filter(|line| line.starts_with("... output truncated"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        notes,
+        ["... output truncated: 2 of 4 rows shown (see .maxrows)"]
+    );
+}
+
+#[test]
+fn database_path_with_spaces_and_unicode() {
+    let dir = std::env::temp_dir().join(format!("bases de données {}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("échantillon.db");
+    std::fs::copy(db_path("sample.db"), &db).unwrap();
+    let output = run(&[db.to_str().unwrap(), "SELECT COUNT(*) FROM apples"], "");
+    assert_eq!(stdout(&output), "4\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // The error names the missing file
+    let missing_db = dir.join("absent.db");
+    let missing_db = missing_db.to_str().unwrap();
+    let output = run(&[missing_db, ".tables"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("unable to open database \"{missing_db}\"")));
+}
+
+#[test]
+fn describe_prints_columns_indexes_and_row_count() {
+    let output = run(&[&db_path("misc.db"), ".describe fruits"], "");
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "columns:\n  id INTEGER\n  name TEXT\n  color TEXT\n\
+         indexes: idx_fruits_color idx_fruits_color_name\n\
+         number of rows: 7\n"
+    );
+}
+
+#[test]
+fn explain_writes_the_plan_to_stderr_and_rows_to_stdout() {
+    let output = run(
+        &[
+            "--explain",
+            &db_path("misc.db"),
+            "SELECT name FROM fruits WHERE id = 2",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "banana\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr
+        .lines()
+        .any(|line| line == "plan: SEARCH fruits USING INTEGER PRIMARY KEY (rowid=?)"));
+}
+
+#[test]
+fn init_file_sets_the_output_mode() {
+    let init_file = std::env::temp_dir().join(format!("init-{}.sql", std::process::id()));
+    std::fs::write(&init_file, ".mode csv\n.headers on\n").unwrap();
+    let init_file = init_file.to_str().unwrap();
+
+    let sql = "SELECT name, color FROM apples WHERE id < 3";
+    let output = run(&["--init", init_file, &db_path("sample.db"), sql], "");
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "name,color\n\"Granny Smith\",\"Light Green\"\nFuji,Red\n"
+    );
+    std::fs::remove_file(init_file).unwrap();
+
+    let output = run(&["--init", init_file, &db_path("sample.db"), sql], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("cannot open init file \"{init_file}\"")));
+}
+
+#[test]
+fn bail_stops_the_script_at_the_first_error() {
+    let script = "SELECT bogus FROM apples;\nSELECT COUNT(*) FROM apples;\n";
+    let sample_db = db_path("sample.db");
+
+    let output = run(&["-batch", &sample_db], &format!(".bail on\n{script}"));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: no such column: bogus (in table apples)\n"));
+
+    // Off by default, like sqlite3
+    for setting in [".bail off\n", ""] {
+        let output = run(&["-batch", &sample_db], &format!("{setting}{script}"));
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "4\n");
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("Error: no such column: bogus (in table apples)\n"));
+    }
+}
+
+#[test]
+fn echo_prints_each_statement_before_its_results() {
+    let script = "SELECT name FROM apples WHERE id = 1;\n\
+                  SELECT name FROM apples\n  WHERE id = 2;\n\
+                  .echo off\n\
+                  SELECT name FROM apples WHERE id = 3;\n";
+    let output = run(
+        &["-batch", &db_path("sample.db")],
+        &format!(".echo on\n{script}"),
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "SELECT name FROM apples WHERE id = 1;\nGranny Smith\n\
+         SELECT name FROM apples\n  WHERE id = 2;\nFuji\n\
+         .echo off\nHoneycrisp\n"
+    );
+
+    // The '-echo' flag turns it on from the start
+    let output = run(&["-batch", "-echo", &db_path("sample.db")], script);
+    assert!(stdout(&output).starts_with("SELECT name FROM apples WHERE id = 1;\nGranny Smith\n"));
+}
+
+#[test]
+fn dbinfo_warns_about_a_stale_header_page_count() {
+    let output = run(&[&db_path("sample.db"), ".dbinfo"], "");
+    assert!(stdout(&output).contains("database page count: 4\n"));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+    // The header records 9 pages
+    let mut bytes = std::fs::read(db_path("sample.db")).unwrap();
+    bytes[28..32].copy_from_slice(&9u32.to_be_bytes());
+    let stale_db = std::env::temp_dir().join(format!("stale-{}.db", std::process::id()));
+    std::fs::write(&stale_db, bytes).unwrap();
+
+    let output = run(&[stale_db.to_str().unwrap(), ".dbinfo"], "");
+    std::fs::remove_file(&stale_db).unwrap();
+    assert!(output.status.success());
+    // The file size wins
+    assert!(stdout(&output).contains("database page count: 4\n"));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("warning: the header records 9 pages, but the file holds 4"));
+}
@@ -0,0 +1,189 @@
+#!/bin/sh
+# Build the test databases of this directory with sqlite3. The tests read them as checked in:
+# run this script only to change them.
+set -e
+cd "$(dirname "$0")"
+
+# Build a database from the SQL read on stdin, replacing any previous version
+make_db() {
+    rm -f "$1"
+    sqlite3 "$1"
+}
+
+# Small tables, one per feature
+make_db misc.db <<'SQL'
+PRAGMA page_size = 1024;
+CREATE TABLE flags (
+    id INTEGER PRIMARY KEY,
+    name TEXT,
+    flags INTEGER
+);
+INSERT INTO flags VALUES (1, 'read', 15), (2, 'all', 255), (3, 'none', 0);
+CREATE TABLE signed (
+    id INTEGER PRIMARY KEY,
+    amount INTEGER
+);
+INSERT INTO signed VALUES (-5, -1), (-1, -300000), (3, -9223372036854775808);
+CREATE TABLE fruits (
+    id INTEGER PRIMARY KEY,
+    name TEXT,
+    color TEXT
+);
+CREATE INDEX idx_fruits_color ON fruits (color);
+CREATE INDEX idx_fruits_color_name ON fruits (color, name);
+INSERT INTO fruits VALUES
+    (1, 'apple', 'red'), (2, 'banana', 'yellow'), (3, 'cherry', 'red'), (4, 'durian', NULL),
+    (5, 'lemon', 'yellow'), (6, 'lime', 'green'), (7, 'mystery', NULL);
+CREATE TABLE empty (
+    id INTEGER PRIMARY KEY,
+    name TEXT
+);
+CREATE TABLE orders (
+    id INTEGER PRIMARY KEY,
+    fruit_id INTEGER
+);
+INSERT INTO orders (fruit_id) VALUES (1), (3), (3), (9);
+CREATE TABLE words (
+    id INTEGER PRIMARY KEY,
+    word TEXT
+);
+INSERT INTO words (word) VALUES ('a'), ('b'), ('c''s'), ('d'), ('B'), ('Apple');
+SQL
+
+# A table spanning many pages: its b-tree root is an interior page, over 63 leaves
+make_db pages.db <<'SQL'
+PRAGMA page_size = 512;
+CREATE TABLE numbers (
+    id INTEGER PRIMARY KEY,
+    label TEXT
+);
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000)
+INSERT INTO numbers SELECT i, 'number ' || i || ' of the table' FROM n;
+SQL
+
+# An auto_vacuum database: pointer-map pages, which are not b-tree pages, come before the table
+# pages. With 512-byte pages, pages 2 and 105 are pointer-map pages.
+make_db autovacuum.db <<'SQL'
+PRAGMA page_size = 512;
+PRAGMA auto_vacuum = FULL;
+CREATE TABLE wide_rows (
+    id INTEGER PRIMARY KEY,
+    padding TEXT
+);
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 250)
+INSERT INTO wide_rows SELECT i, replace(hex(zeroblob(100)), '0', 'x') FROM n;
+SQL
+
+# A deleted row leaves a freeblock in the middle of the cell content area of the table page
+make_db freeblocks.db <<'SQL'
+PRAGMA page_size = 512;
+CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+INSERT INTO notes VALUES (1, 'first note'), (2, 'second note'), (3, 'third note');
+DELETE FROM notes WHERE id = 2;
+SQL
+
+# Views over a table: a supported single-table view, and an unsupported join
+make_db views.db <<'SQL'
+CREATE TABLE fruits (id INTEGER PRIMARY KEY, name TEXT, color TEXT);
+INSERT INTO fruits VALUES (1, 'apple', 'red'), (2, 'banana', 'yellow'), (3, 'cherry', 'red');
+CREATE TABLE orders (id INTEGER PRIMARY KEY, fruit_id INTEGER);
+INSERT INTO orders (fruit_id) VALUES (1), (3);
+CREATE VIEW red_fruits AS SELECT id, name FROM fruits WHERE color = 'red';
+CREATE VIEW fruit_orders AS SELECT orders.id, fruits.name FROM orders JOIN fruits ON fruits.id = orders.fruit_id;
+SQL
+
+# Real values
+make_db reals.db <<'SQL'
+CREATE TABLE measures (id INTEGER PRIMARY KEY, name TEXT, value REAL);
+INSERT INTO measures VALUES
+    (1, 'tiny', 0.02), (2, 'small', 1.5), (3, 'large', 1500.0), (4, 'huge', 6.02e23);
+SQL
+
+# Enough tables to spill the schema over several pages: page 1 becomes an interior page
+{
+    echo "PRAGMA page_size = 512;"
+    for i in $(seq 1 40); do
+        echo "CREATE TABLE table_$i (id INTEGER PRIMARY KEY, name TEXT);"
+    done
+    echo "INSERT INTO table_40 VALUES (1, 'last');"
+} | make_db many_tables.db
+
+# Text encoded as UTF-16le
+make_db utf16le.db <<'SQL'
+PRAGMA encoding = 'UTF-16le';
+CREATE TABLE greetings (id INTEGER PRIMARY KEY, greeting TEXT);
+INSERT INTO greetings VALUES (1, 'hello'), (2, 'héllo wörld'), (3, '日本語');
+SQL
+
+# A column without a declared type, holding a value of each storage class
+make_db types.db <<'SQL'
+CREATE TABLE mixed (id INTEGER PRIMARY KEY, value);
+INSERT INTO mixed VALUES (1, NULL), (2, 42), (3, 2.5), (4, 'text'), (5, x'00ff'), (6, -7), (7, '');
+SQL
+
+# Header fields set by the application
+make_db versioned.db <<'SQL'
+PRAGMA user_version = 7;
+PRAGMA application_id = 1936024932;
+CREATE TABLE settings (key TEXT, value TEXT);
+SQL
+
+# Payloads around the maximum stored in a table leaf cell, 512 - 35 = 477 bytes with 512-byte pages.
+# A record of a NULL rowid alias and a text of 473 bytes takes 4 + 473 bytes: it fits exactly, in
+# the last bytes of the file. One more byte spills onto an overflow page.
+make_db payloads.db <<'SQL'
+PRAGMA page_size = 512;
+CREATE TABLE spilled (id INTEGER PRIMARY KEY, body TEXT);
+INSERT INTO spilled VALUES (1, substr(replace(hex(zeroblob(300)), '0', 'x'), 1, 474));
+CREATE TABLE exact_fit (id INTEGER PRIMARY KEY, body TEXT);
+INSERT INTO exact_fit VALUES (1, substr(replace(hex(zeroblob(300)), '0', 'x'), 1, 473));
+SQL
+
+# Columns added after the first rows: the records of those rows are shorter than the schema
+make_db altered.db <<'SQL'
+CREATE TABLE tasks (id INTEGER PRIMARY KEY, title TEXT);
+INSERT INTO tasks VALUES (1, 'write'), (2, 'test');
+ALTER TABLE tasks ADD COLUMN priority INTEGER;
+ALTER TABLE tasks ADD COLUMN status TEXT DEFAULT 'todo';
+INSERT INTO tasks VALUES (3, 'ship', 1, 'done');
+SQL
+
+# A CREATE statement too long for its schema cell: the 'sql' column spills onto overflow pages
+{
+    echo "PRAGMA page_size = 512;"
+    columns=$(seq -s ', ' -f 'column_%g TEXT' 1 60)
+    echo "CREATE TABLE wide ($columns);"
+    echo "INSERT INTO wide (column_1, column_60) VALUES ('first', 'last');"
+} | make_db wide.db
+
+# Planner statistics, written to 'sqlite_stat1' by ANALYZE
+make_db analyzed.db <<'SQL'
+CREATE TABLE cities (id INTEGER PRIMARY KEY, name TEXT, country TEXT);
+CREATE INDEX idx_cities_country ON cities (country);
+INSERT INTO cities (name, country) VALUES
+    ('Paris', 'FR'), ('Lyon', 'FR'), ('Nice', 'FR'), ('Berlin', 'DE'), ('Munich', 'DE'),
+    ('Rome', 'IT');
+ANALYZE;
+SQL
+
+# Rows inserted out of rowid order: each new cell is written below the previous ones, so the cell
+# of row 2, inserted last, lies before the cell of row 3 while its pointer comes after
+make_db shuffled.db <<'SQL'
+CREATE TABLE letters (id INTEGER PRIMARY KEY, letter TEXT);
+INSERT INTO letters VALUES (1, 'a');
+INSERT INTO letters VALUES (3, 'c');
+INSERT INTO letters VALUES (2, 'b');
+SQL
+
+# A blob of 20000 bytes, spilling onto overflow pages, and one of 1000 bytes fitting in its cell
+make_db blobs.db <<'SQL'
+CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT, data BLOB);
+INSERT INTO files VALUES (1, 'zeros.bin', zeroblob(20000));
+INSERT INTO files VALUES (2, 'ones.bin', CAST(replace(hex(zeroblob(500)), '0', '1') AS BLOB));
+SQL
+
+# Two numeric columns to compare with each other
+make_db ranges.db <<'SQL'
+CREATE TABLE ranges (id INTEGER PRIMARY KEY, label TEXT, low INTEGER, high INTEGER);
+INSERT INTO ranges (label, low, high) VALUES ('ordered', 1, 5), ('reversed', 7, 3), ('point', 4, 4);
+SQL